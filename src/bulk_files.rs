@@ -1,13 +1,194 @@
-use std::time::Duration;
+use std::{
+    fmt, fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    Client,
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
-use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
-use crate::{card::CardObject, search::APP_USER_AGENT};
+use crate::{
+    card::CardObject,
+    client::{RequestError, ScryfallClient},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::ResponseList,
+        client::{stand_in, Mode, TestFolder},
+    };
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn test_read_cards() {
+        let json_str = fs::read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let folder = TestFolder::new("land-calc-test-bulk");
+        let path = folder.join("bulk.json");
+        let mut cards = serde_json::to_value(&response.data).unwrap();
+        // a card missing a field every card needs is left out, the rest are still read
+        cards
+            .as_array_mut()
+            .unwrap()
+            .insert(1, serde_json::json!({"object": "card", "name": "Broken"}));
+        fs::write(&path, serde_json::to_vec(&cards).unwrap()).unwrap();
+
+        let mut names = vec![];
+        let count = read_cards(&path, |card| names.push(card.name)).unwrap();
+
+        assert_eq!(
+            count,
+            CardCount {
+                read: response.data.len(),
+                skipped: 1
+            }
+        );
+        assert_eq!(names[0], "Tasigur, the Golden Fang");
+    }
+
+    #[tokio::test]
+    async fn test_gzip_download() {
+        let folder = TestFolder::new("land-calc-test-bulk-gzip");
+        let json_str = fs::read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let cards = serde_json::to_vec(&response.data).unwrap();
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(&cards).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        // scryfall gives the size of the file once it's decoded
+        let url = stand_in(|url| {
+            let index = serde_json::json!({"object": "list", "has_more": false, "data": [{
+                "type": "oracle_cards",
+                "download_uri": format!("{}/oracle-cards.json", url),
+                "updated_at": "2024-04-01T09:00:00.000+00:00",
+                "size": cards.len(),
+                "content_encoding": "gzip"
+            }]})
+            .to_string();
+            let download = format!(
+                "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+                gzipped.len()
+            );
+            vec![
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    index.len(),
+                    index
+                )
+                .into_bytes(),
+                [download.into_bytes(), gzipped].concat(),
+            ]
+        })
+        .await;
+        let client = ScryfallClient::new(&url, Mode::Live).unwrap();
+
+        let mut downloaded = 0;
+        let bulk = update_bulk(&client, &folder, BulkKind::OracleCards, |done, _| {
+            downloaded = done
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(downloaded, cards.len() as u64);
+        assert_eq!(fs::read(&bulk.path).unwrap(), cards);
+        assert_eq!(
+            read_cards(&bulk.path, |_| ()).unwrap().read,
+            response.data.len()
+        );
+    }
+
+    #[test]
+    fn test_bulk_kinds() {
+        let index: BulkIndex = serde_json::from_str(
+            r#"{"object": "list", "has_more": false, "data": [
+                {"type": "oracle_cards", "download_uri": "oracle"},
+                {"type": "something_new", "download_uri": "new"},
+                {"download_uri": "untyped"}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(index.data[1].data_type, BulkKind::Unknown);
+        assert_eq!(index.data[2].data_type, BulkKind::Unknown);
+        assert_eq!(
+            index.find(BulkKind::OracleCards).unwrap().download_uri,
+            "oracle"
+        );
+        assert!(matches!(
+            index.find(BulkKind::AllCards),
+            Err(BulkError::MissingKind(BulkKind::AllCards))
+        ));
+        assert_eq!("default_cards".parse(), Ok(BulkKind::DefaultCards));
+        assert!("rulings".parse::<BulkKind>().is_err());
+    }
+}
+
+/// the bulk files scryfall publishes
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkKind {
+    /// one printing of every card, the smallest
+    #[default]
+    OracleCards,
+    UniqueArtwork,
+    /// every printing in english
+    DefaultCards,
+    /// every printing in every language, several gigabytes
+    AllCards,
+    Rulings,
+    /// a kind added after this was written
+    #[serde(other)]
+    Unknown,
+}
+
+impl BulkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OracleCards => "oracle_cards",
+            Self::UniqueArtwork => "unique_artwork",
+            Self::DefaultCards => "default_cards",
+            Self::AllCards => "all_cards",
+            Self::Rulings => "rulings",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for BulkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// only the kinds that are lists of cards
+impl FromStr for BulkKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Self::OracleCards,
+            Self::UniqueArtwork,
+            Self::DefaultCards,
+            Self::AllCards,
+        ]
+        .into_iter()
+        .find(|kind| kind.as_str() == s)
+        .ok_or(format!(
+            "{} is not a bulk file of cards, try oracle_cards, unique_artwork, default_cards or all_cards",
+            s
+        ))
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct BulkIndex {
@@ -27,9 +208,10 @@ struct BulkItem {
     id: Uuid,
     #[serde(default)]
     uri: String, // URI
-    #[serde(default)]
+    /// an entry without a type is no kind `find` looks for
+    #[serde(default = "unknown_kind")]
     #[serde(rename = "type")]
-    data_type: String,
+    data_type: BulkKind,
     #[serde(default)]
     name: String,
     #[serde(default)]
@@ -38,50 +220,197 @@ struct BulkItem {
     download_uri: String, // URI
     #[serde(default)]
     updated_at: String,
+    /// in bytes, all_cards is bigger than an i32
     #[serde(default)]
-    size: i32,
+    size: u64,
     #[serde(default)]
     content_type: String,
     #[serde(default)]
     content_encoding: String,
 }
 
+/// `BulkKind::default()` is what the command line falls back on, not a guess at a missing type
+fn unknown_kind() -> BulkKind {
+    BulkKind::Unknown
+}
+
+impl BulkIndex {
+    fn find(&self, kind: BulkKind) -> Result<&BulkItem, BulkError> {
+        self.data
+            .iter()
+            .find(|o| o.data_type == kind)
+            .ok_or(BulkError::MissingKind(kind))
+    }
+}
+
 const TIMEOUT: u64 = 10;
+const BULK_PATH: &str = "/bulk-data";
+pub const BULK_FOLDER: &str = "bulk";
+
+#[derive(Debug)]
+pub enum BulkError {
+    Request(RequestError),
+    Io(PathBuf, io::Error),
+    Json(PathBuf, serde_json::Error),
+    /// scryfall doesn't publish this kind of bulk file right now
+    MissingKind(BulkKind),
+    /// the download ended at `got` bytes, the bulk index promised `expected`
+    WrongSize {
+        expected: u64,
+        got: u64,
+    },
+}
 
-pub async fn get_bulk_from_scryfall() -> Result<Vec<CardObject>, reqwest::Error> {
-    let mut headers = HeaderMap::new();
-    headers.insert("User-Agent", HeaderValue::from_static(APP_USER_AGENT));
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "{}", e),
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Json(path, e) => write!(f, "{} is not a list of cards: {}", path.display(), e),
+            Self::MissingKind(kind) => write!(f, "scryfall has no {} bulk file", kind),
+            Self::WrongSize { expected, got } => write!(
+                f,
+                "bulk download is {} bytes, scryfall said it would be {}",
+                got, expected
+            ),
+        }
+    }
+}
+
+impl From<RequestError> for BulkError {
+    fn from(value: RequestError) -> Self {
+        BulkError::Request(value)
+    }
+}
 
-    let https_client = Client::builder()
-        .default_headers(headers)
-        .https_only(true)
-        .timeout(Duration::from_secs(TIMEOUT))
-        .build()?;
+impl From<reqwest::Error> for BulkError {
+    fn from(value: reqwest::Error) -> Self {
+        BulkError::Request(value.into())
+    }
+}
 
-    let url = "https://api.scryfall.com/bulk-data";
+/// a bulk file on disk, and the version of it scryfall published
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkFile {
+    pub path: PathBuf,
+    pub updated_at: String,
+}
 
-    let response = https_client
-        .get(url)
-        .send()
+/// makes sure `folder` holds the newest bulk file of this kind, downloading it only when scryfall has a newer one.
+/// `progress` is told the bytes downloaded so far and the total after every chunk
+pub async fn update_bulk(
+    client: &ScryfallClient,
+    folder: &Path,
+    kind: BulkKind,
+    mut progress: impl FnMut(u64, u64),
+) -> Result<BulkFile, BulkError> {
+    let response = client
+        .send(client.get(BULK_PATH).timeout(Duration::from_secs(TIMEOUT)))
         .await?
         .json::<BulkIndex>()
         .await?;
 
-    let next_item: &BulkItem = response
-        .data
-        .iter()
-        .filter(|o| o.data_type == "oracle_cards")
-        .next()
-        .unwrap();
+    let next_item: &BulkItem = response.find(kind)?;
 
-    let next_url: String = next_item.download_uri.to_string();
+    let path = bulk_path(folder, kind);
+    let meta_path = folder.join(format!("{}.meta.json", kind));
+    let bulk_file = BulkFile {
+        path: path.clone(),
+        updated_at: next_item.updated_at.clone(),
+    };
 
-    let cards = https_client
-        .get(next_url)
-        .send()
-        .await?
-        .json::<Vec<CardObject>>()
+    let stored: Option<BulkItem> = fs::read(&meta_path)
+        .ok()
+        .and_then(|meta| serde_json::from_slice(&meta).ok());
+    let on_disk = fs::metadata(&path).map(|m| m.len()).ok();
+    if stored.is_some_and(|s| s.updated_at == next_item.updated_at && Some(s.size) == on_disk) {
+        return Ok(bulk_file);
+    }
+
+    fs::create_dir_all(folder).map_err(|e| BulkError::Io(folder.to_path_buf(), e))?;
+    // the old file stays usable until the new one is complete
+    let part_path = path.with_extension("json.part");
+    let io_error = |e| BulkError::Io(part_path.clone(), e);
+
+    // no timeout, the whole file takes a while.
+    // scryfall sends it gzip encoded, reqwest decodes it so `written` counts the same bytes `size` does
+    let mut download = client
+        .send_unrecorded(client.download(&next_item.download_uri))
         .await?;
+    let mut file = tokio::fs::File::create(&part_path)
+        .await
+        .map_err(io_error)?;
+    let mut written: u64 = 0;
+    while let Some(chunk) = download.chunk().await? {
+        file.write_all(&chunk).await.map_err(io_error)?;
+        written += chunk.len() as u64;
+        progress(written, next_item.size);
+    }
+    file.flush().await.map_err(io_error)?;
+    drop(file);
+
+    if written != next_item.size {
+        let _ = fs::remove_file(&part_path);
+        return Err(BulkError::WrongSize {
+            expected: next_item.size,
+            got: written,
+        });
+    }
+
+    fs::rename(&part_path, &path).map_err(|e| BulkError::Io(path.clone(), e))?;
+    let meta = serde_json::to_vec_pretty(next_item).expect("bulk items serialize");
+    fs::write(&meta_path, meta).map_err(|e| BulkError::Io(meta_path.clone(), e))?;
+
+    Ok(bulk_file)
+}
+
+/// where `update_bulk` keeps the bulk file of this kind
+pub fn bulk_path(folder: &Path, kind: BulkKind) -> PathBuf {
+    folder.join(format!("{}.json", kind))
+}
+
+/// how many cards `read_cards` handed on, and how many it had to leave out
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CardCount {
+    pub read: usize,
+    /// elements that aren't a card this program understands, like reversible cards without top-level fields
+    pub skipped: usize,
+}
+
+/// hands every card in a bulk file to `on_card` as it is read, so the whole file is never in memory.
+/// a card that doesn't deserialize is skipped and counted instead of ending the read
+pub fn read_cards(path: &Path, on_card: impl FnMut(CardObject)) -> Result<CardCount, BulkError> {
+    let file = fs::File::open(path).map_err(|e| BulkError::Io(path.to_path_buf(), e))?;
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+
+    deserializer
+        .deserialize_seq(CardsVisitor(on_card))
+        .map_err(|e| BulkError::Json(path.to_path_buf(), e))
+}
+
+struct CardsVisitor<F>(F);
+
+impl<'de, F: FnMut(CardObject)> Visitor<'de> for CardsVisitor<F> {
+    type Value = CardCount;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of scryfall cards")
+    }
 
-    Ok(cards)
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut count = CardCount::default();
+        while let Some(value) = seq.next_element::<serde_json::Value>()? {
+            match serde_json::from_value::<CardObject>(value) {
+                Ok(card) => {
+                    (self.0)(card);
+                    count.read += 1;
+                }
+                Err(_) => count.skipped += 1,
+            }
+        }
+        Ok(count)
+    }
 }
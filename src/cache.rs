@@ -0,0 +1,427 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    card::{CardObject, SearchResult},
+    names,
+    search::{printing_key, CardQuery},
+};
+use uuid::Uuid;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card::ResponseList, client::TestFolder};
+    use std::fs::read_to_string;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let tasigur = &response.data[0];
+        let folder = TestFolder::new("land-calc-test-cache");
+        let path = folder.join("cache.json");
+        let printing = CardQuery::Printing {
+            name: "Tasigur, the Golden Fang".to_string(),
+            set: "uma".to_string(),
+            collector_number: "117".to_string(),
+        };
+
+        let found = SearchResult::OneHit(tasigur.clone());
+
+        let mut cache = CardCache::load(path.clone(), Duration::from_secs(60));
+        cache.insert(&printing, &found);
+        cache.save().unwrap();
+
+        let cache = CardCache::load(path.clone(), Duration::from_secs(60));
+
+        let by_name = CardQuery::Name("Tasigur, the Golden Fang".to_string());
+        assert!(matches!(cache.get(&printing), Some(SearchResult::OneHit(c)) if &c == tasigur));
+        // the card is cached by its oracle_id too, so asking for it by name needs no lookup
+        assert!(matches!(cache.get(&by_name), Some(SearchResult::OneHit(c)) if &c == tasigur));
+        assert!(cache.get(&CardQuery::Name("Opt".to_string())).is_none());
+
+        let mut cache = cache;
+        cache.insert(&by_name, &found);
+        assert!(matches!(
+            cache.get(&CardQuery::Name("TASIGUR, THE GOLDEN FANG".to_string())),
+            Some(SearchResult::OneHit(c)) if &c == tasigur
+        ));
+
+        let expired = CardCache {
+            ttl: Duration::ZERO,
+            ..cache
+        };
+        assert!(expired.get(&printing).is_none());
+    }
+
+    #[test]
+    fn test_cache_keeps_the_printing() {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let tasigur = response.data[0].clone();
+        let reprint = CardObject {
+            set: "frf".to_string(),
+            collector_number: "83".to_string(),
+            ..tasigur.clone()
+        };
+        let japanese = CardObject {
+            lang: "ja".to_string(),
+            printed_name: Some("黄金牙、タシグル".to_string()),
+            ..tasigur.clone()
+        };
+        let folder = TestFolder::new("land-calc-test-cache-printing");
+        let path = folder.join("cache.json");
+        let by_name = CardQuery::Name("Tasigur, the Golden Fang".to_string());
+        let by_printing = CardQuery::Printing {
+            name: "Tasigur, the Golden Fang".to_string(),
+            set: "frf".to_string(),
+            collector_number: "83".to_string(),
+        };
+        let by_japanese_name = CardQuery::Name("黄金牙、タシグル".to_string());
+        let english_printing = CardQuery::Printing {
+            name: "Tasigur, the Golden Fang".to_string(),
+            set: "uma".to_string(),
+            collector_number: "117".to_string(),
+        };
+
+        // a later printing of the same card doesn't change what the name resolved to
+        let mut cache = CardCache::load(path.clone(), Duration::from_secs(60));
+        cache.insert(&by_name, &SearchResult::OneHit(tasigur.clone()));
+        cache.insert(&by_printing, &SearchResult::OneHit(reprint.clone()));
+        // another language of the same printing is kept next to the english one
+        cache.insert(&by_japanese_name, &SearchResult::OneHit(japanese.clone()));
+        cache.save().unwrap();
+        let cache = CardCache::load(path.clone(), Duration::from_secs(60));
+
+        assert!(matches!(cache.get(&by_name), Some(SearchResult::OneHit(c)) if c == tasigur));
+        assert!(matches!(cache.get(&by_printing), Some(SearchResult::OneHit(c)) if c == reprint));
+        assert!(
+            matches!(cache.get(&by_japanese_name), Some(SearchResult::OneHit(c)) if c == japanese)
+        );
+        assert!(
+            matches!(cache.get(&english_printing), Some(SearchResult::OneHit(c)) if c == tasigur)
+        );
+    }
+
+    #[test]
+    fn test_cache_misses_and_ambiguous_names() {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let tasigur = response.data[0].clone();
+        let folder = TestFolder::new("land-calc-test-cache-misses");
+        let path = folder.join("cache.json");
+        let typo = CardQuery::Name("Tasgur".to_string());
+        let short = CardQuery::Name("Tasigur".to_string());
+        let not_found = SearchResult::NoHits("Tasgur".to_string(), vec![tasigur.name.clone()]);
+        let ambiguous = SearchResult::MultipleHits(
+            "Tasigur".to_string(),
+            2,
+            tasigur.clone(),
+            vec![tasigur.clone()],
+        );
+
+        let mut cache = CardCache::load(path.clone(), Duration::from_secs(60));
+        cache.insert(&typo, &not_found);
+        cache.insert(&short, &ambiguous);
+        cache.save().unwrap();
+        let mut cache = CardCache::load(path.clone(), Duration::from_secs(60));
+
+        assert!(matches!(
+            cache.get(&typo),
+            Some(SearchResult::NoHits(search, suggestions)) if search == "Tasgur" && suggestions == [tasigur.name.clone()]
+        ));
+        assert!(matches!(
+            cache.get(&short),
+            Some(SearchResult::MultipleHits(_, 2, chosen, candidates)) if chosen == tasigur && candidates == [tasigur.clone()]
+        ));
+
+        cache.ttl = Duration::ZERO;
+        cache.save().unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+
+        assert!(!saved.contains("Tasgur"));
+        assert!(!saved.contains(&tasigur.name));
+    }
+}
+
+/// bump this whenever `CardObject`, `names::fold` or the layout below change, older cache files are thrown away
+const CACHE_VERSION: u32 = 5;
+pub const CACHE_FILE: &str = "card_cache.json";
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+struct CachedCard {
+    /// seconds since the unix epoch
+    fetched_at: u64,
+    card: CardObject,
+}
+
+/// what a query resolved to, by the "set/collector_number/lang" of each printing, the cards themselves are kept in `printings`
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+enum Answer {
+    Card(String),
+    /// several cards went by the name, `chosen` is the one that was used
+    Ambiguous {
+        search: String,
+        total: u32,
+        chosen: String,
+        candidates: Vec<String>,
+    },
+    NotFound {
+        search: String,
+        suggestions: Vec<String>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+struct CachedQuery {
+    /// seconds since the unix epoch
+    fetched_at: u64,
+    answer: Answer,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+struct CacheFile {
+    version: u32,
+    /// what each query resolved to
+    queries: HashMap<String, CachedQuery>,
+    /// every cached printing, by "set/collector_number/lang", languages of a printing share a set and number
+    printings: HashMap<String, CachedCard>,
+    /// the printing each cached card was last seen as, by oracle_id
+    oracle_cards: HashMap<Uuid, String>,
+}
+
+impl Default for CacheFile {
+    fn default() -> Self {
+        CacheFile {
+            version: CACHE_VERSION,
+            queries: HashMap::new(),
+            printings: HashMap::new(),
+            oracle_cards: HashMap::new(),
+        }
+    }
+}
+
+/// lookups saved between runs, so an unchanged decklist doesn't need the network
+pub struct CardCache {
+    path: PathBuf,
+    ttl: Duration,
+    file: CacheFile,
+}
+
+impl CardCache {
+    /// a missing, unreadable or outdated cache file just means an empty cache
+    pub fn load(path: PathBuf, ttl: Duration) -> Self {
+        let file = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheFile>(&bytes).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .unwrap_or_default();
+
+        CardCache { path, ttl, file }
+    }
+
+    /// writes the cache, leaving out everything that has expired
+    pub fn save(&mut self) -> io::Result<()> {
+        let (now, ttl) = (now(), self.ttl);
+        self.file
+            .queries
+            .retain(|_, cached| is_fresh(cached.fetched_at, now, ttl));
+        self.file
+            .printings
+            .retain(|_, cached| is_fresh(cached.fetched_at, now, ttl));
+        let printings = &self.file.printings;
+        self.file
+            .oracle_cards
+            .retain(|_, printing| printings.contains_key(printing));
+
+        fs::write(&self.path, serde_json::to_vec(&self.file)?)
+    }
+
+    /// the answer scryfall gave last time, as long as it's fresh.
+    /// a name no query was cached under is answered by a cached card that goes by it
+    pub fn get(&self, query: &CardQuery) -> Option<SearchResult> {
+        if let CardQuery::Printing {
+            name,
+            set,
+            collector_number,
+        } = query
+        {
+            let key = printing_key(set, collector_number);
+            // the english printing when there are several languages of it
+            let printing = self
+                .file
+                .printings
+                .iter()
+                .filter(|(printing, cached)| {
+                    printing.rsplit_once('/').is_some_and(|(p, _)| p == key)
+                        && self.fresh(cached.fetched_at)
+                        && cached.card.has_name(name)
+                })
+                .min_by_key(|(_, cached)| cached.card.lang != "en");
+
+            if let Some((_, cached)) = printing {
+                return Some(SearchResult::OneHit(cached.card.clone()));
+            }
+        }
+
+        if let CardQuery::OracleId { oracle_id, .. } = query {
+            let card = self
+                .file
+                .oracle_cards
+                .get(oracle_id)
+                .and_then(|printing| self.file.printings.get(printing))
+                .filter(|cached| self.fresh(cached.fetched_at));
+
+            if let Some(cached) = card {
+                return Some(SearchResult::OneHit(cached.card.clone()));
+            }
+        }
+
+        let card = |printing: &String| {
+            self.file
+                .printings
+                .get(printing)
+                .filter(|cached| self.fresh(cached.fetched_at))
+                .map(|cached| cached.card.clone())
+        };
+        let Some(cached) = self
+            .file
+            .queries
+            .get(&query_key(query))
+            .filter(|cached| self.fresh(cached.fetched_at))
+        else {
+            return match query {
+                CardQuery::Name(name) => self.card_named(name),
+                CardQuery::Printing { .. } | CardQuery::OracleId { .. } => None,
+            };
+        };
+
+        let result = match &cached.answer {
+            Answer::Card(printing) => SearchResult::OneHit(card(printing)?),
+            Answer::Ambiguous {
+                search,
+                total,
+                chosen,
+                candidates,
+            } => SearchResult::MultipleHits(
+                search.clone(),
+                *total,
+                card(chosen)?,
+                candidates.iter().map(card).collect::<Option<_>>()?,
+            ),
+            Answer::NotFound {
+                search,
+                suggestions,
+            } => SearchResult::NoHits(search.clone(), suggestions.clone()),
+        };
+        Some(result)
+    }
+
+    /// the one cached card going by `name`, `None` when there are none or several
+    fn card_named(&self, name: &str) -> Option<SearchResult> {
+        let mut named = self
+            .file
+            .oracle_cards
+            .values()
+            .filter_map(|printing| self.file.printings.get(printing))
+            .filter(|cached| self.fresh(cached.fetched_at) && cached.card.has_name(name));
+
+        match (named.next(), named.next()) {
+            (Some(cached), None) => Some(SearchResult::OneHit(cached.card.clone())),
+            _ => None,
+        }
+    }
+
+    /// remembers what `query` resolved to, a failed lookup is tried again next time
+    pub fn insert(&mut self, query: &CardQuery, result: &SearchResult) {
+        let answer = match result {
+            SearchResult::OneHit(card) => {
+                self.insert_card(card);
+                Answer::Card(card_key(card))
+            }
+            SearchResult::MultipleHits(search, total, chosen, candidates) => {
+                self.insert_card(chosen);
+                candidates.iter().for_each(|card| self.insert_card(card));
+                Answer::Ambiguous {
+                    search: search.clone(),
+                    total: *total,
+                    chosen: card_key(chosen),
+                    candidates: candidates.iter().map(card_key).collect(),
+                }
+            }
+            SearchResult::NoHits(search, suggestions) => Answer::NotFound {
+                search: search.clone(),
+                suggestions: suggestions.clone(),
+            },
+            SearchResult::Failed(_, _) => return,
+        };
+
+        self.file.queries.insert(
+            query_key(query),
+            CachedQuery {
+                fetched_at: now(),
+                answer,
+            },
+        );
+    }
+
+    fn insert_card(&mut self, card: &CardObject) {
+        self.file.printings.insert(
+            card_key(card),
+            CachedCard {
+                fetched_at: now(),
+                card: card.clone(),
+            },
+        );
+        self.file
+            .oracle_cards
+            .insert(card.oracle_key(), card_key(card));
+    }
+
+    fn fresh(&self, fetched_at: u64) -> bool {
+        is_fresh(fetched_at, now(), self.ttl)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn is_fresh(fetched_at: u64, now: u64, ttl: Duration) -> bool {
+    now.saturating_sub(fetched_at) < ttl.as_secs()
+}
+
+/// a query answers with the printing it resolved to, in the language it was found in
+fn card_key(card: &CardObject) -> String {
+    format!(
+        "{}/{}",
+        printing_key(&card.set, &card.collector_number),
+        card.lang
+    )
+}
+
+fn query_key(query: &CardQuery) -> String {
+    match query {
+        CardQuery::Printing {
+            name,
+            set,
+            collector_number,
+        } => format!(
+            "{} ({})",
+            names::fold(name),
+            printing_key(set, collector_number)
+        ),
+        CardQuery::Name(name) => names::fold(name),
+        CardQuery::OracleId { oracle_id, .. } => format!("oracle_id {}", oracle_id),
+    }
+}
@@ -1,8 +1,13 @@
 use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Deserializer, Serialize};
 use serde_json::Number;
-use std::{collections::HashMap, fmt};
+use std::{collections::HashMap, fmt, sync::Arc};
 use uuid::Uuid;
 
+use crate::{client::RequestError, names, search::CardQuery};
+
+/// how many of the cards a search found are kept for the user to choose from
+const MAX_CANDIDATES: usize = 5;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -21,12 +26,148 @@ mod tests {
     fn test_tasigur_colour() {
         let json_str: String = read_to_string("test.json").unwrap();
         let response: ResponseList = serde_json::from_str(&json_str).unwrap();
-        let search_result = response.card_or("".to_string());
+        let search_result = response.card_or("".to_string(), "Tasigur, the Golden Fang", None);
         let tasigur = search_result.get_card_ref().unwrap();
 
         assert!(tasigur.get_colours().0 == 0b00000100);
         assert!(tasigur.get_identity().0 == 0b00001101);
     }
+
+    #[test]
+    fn test_exact_name_among_hits() {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let mut response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let mut other = response.data[0].clone();
+        other.name = "Tasigur's Cruelty".to_string();
+        response.data.insert(0, other);
+        response.total_cards = 2;
+
+        let search_result = response.card_or("".to_string(), "tasigur, the golden fang", None);
+        assert!(
+            matches!(search_result, SearchResult::OneHit(c) if c.name == "Tasigur, the Golden Fang")
+        );
+
+        let search_result = response.card_or("".to_string(), "Tasigur", None);
+        assert!(
+            matches!(search_result, SearchResult::MultipleHits(_, 2, _, candidates) if candidates.len() == 2)
+        );
+    }
+
+    #[test]
+    fn test_face_names() {
+        let face = |name: &str| CardFace {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let kellan = CardObject {
+            name: "Kellan, Daring Traveler // Journey On".to_string(),
+            card_faces: vec![face("Kellan, Daring Traveler"), face("Journey On")],
+            ..Default::default()
+        };
+        let response = ResponseList {
+            total_cards: 1,
+            data: vec![kellan],
+            ..Default::default()
+        };
+
+        for name in [
+            "Kellan, Daring Traveler // Journey On",
+            "Kellan, Daring Traveler//Journey On",
+            "Kellan, Daring Traveler",
+            "journey on",
+        ] {
+            assert!(response.data[0].has_name(name));
+            assert!(matches!(
+                response.card_or("".to_string(), name, None),
+                SearchResult::OneHit(_)
+            ));
+        }
+        assert!(!response.data[0].has_name("Kellan"));
+    }
+
+    #[test]
+    fn test_copies_allowed() {
+        let card = |type_line: &str, oracle_text: &str| CardObject {
+            type_line: type_line.to_string(),
+            oracle_text: Some(oracle_text.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            card("Basic Snow Land — Forest", "({T}: Add {G}.)").copies_allowed(),
+            None
+        );
+        assert_eq!(
+            card(
+                "Creature — Rat",
+                "A deck can have any number of cards named Relentless Rats."
+            )
+            .copies_allowed(),
+            None
+        );
+        assert_eq!(
+            card(
+                "Creature — Dwarf",
+                "A deck can have up to seven cards named Seven Dwarves."
+            )
+            .copies_allowed(),
+            Some(7)
+        );
+        assert_eq!(
+            card(
+                "Legendary Creature — Wraith Knight",
+                "A deck can have up to nine cards named Nazgûl."
+            )
+            .copies_allowed(),
+            Some(9)
+        );
+        assert_eq!(
+            card("Instant", "Scry 1.\nDraw a card.").copies_allowed(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_error_response() {
+        let json_str = r#"{
+            "object": "error",
+            "code": "not_found",
+            "status": 404,
+            "details": "Your query didn’t match any cards. Adjust your search terms or refer to the syntax guide at https://scryfall.com/docs/reference"
+        }"#;
+        let response: ScryfallResponse<ResponseList> = serde_json::from_str(json_str).unwrap();
+
+        assert!(matches!(response, ScryfallResponse::Error(e) if e.is_not_found()));
+
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ScryfallResponse<ResponseList> = serde_json::from_str(&json_str).unwrap();
+
+        assert!(matches!(response, ScryfallResponse::Data(list) if list.total_cards == 1));
+    }
+
+    #[test]
+    fn test_collection_not_found() {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let identifiers = vec![
+            CardIdentifier::Name {
+                name: "Tasigurr".to_string(),
+            },
+            CardIdentifier::Printing {
+                set: "uma".to_string(),
+                collector_number: "117".to_string(),
+            },
+        ];
+        let collection = CollectionResponse {
+            object: "list".to_string(),
+            not_found: vec![identifiers[0].clone()],
+            data: response.data,
+        };
+        let found = collection.match_identifiers(&identifiers);
+
+        assert!(found[0].is_none());
+        assert_eq!(found[1].as_ref().unwrap().name, "Tasigur, the Golden Fang");
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
@@ -166,39 +307,188 @@ pub struct CardObject {
     pub source: Option<String>,
 }
 
+/// a card with nothing filled in, for building one from fewer fields than scryfall sends
+impl Default for CardObject {
+    fn default() -> Self {
+        CardObject {
+            arena_id: Default::default(),
+            id: Default::default(),
+            lang: Default::default(),
+            mtgo_id: Default::default(),
+            mtgo_foil_id: Default::default(),
+            multiverse_ids: Default::default(),
+            tcgplayer_id: Default::default(),
+            tcgplayer_etched_id: Default::default(),
+            cardmarket_id: Default::default(),
+            object: Default::default(),
+            layout: Default::default(),
+            oracle_id: Default::default(),
+            prints_search_uri: Default::default(),
+            rulings_uri: Default::default(),
+            scryfall_uri: Default::default(),
+            uri: Default::default(),
+            all_parts: Default::default(),
+            card_faces: Default::default(),
+            cmc: Number::from(0),
+            color_identity: Default::default(),
+            color_indicator: Default::default(),
+            colors: Default::default(),
+            defence: Default::default(),
+            edh_rank: Default::default(),
+            hand_modifier: Default::default(),
+            keywords: Default::default(),
+            legalities: Default::default(),
+            life_modifier: Default::default(),
+            loyalty: Default::default(),
+            mana_cost: Default::default(),
+            name: Default::default(),
+            oracle_text: Default::default(),
+            penny_rank: Default::default(),
+            power: Default::default(),
+            produced_mana: Default::default(),
+            reserved: Default::default(),
+            toughness: Default::default(),
+            type_line: Default::default(),
+            artist: Default::default(),
+            artist_ids: Default::default(),
+            attraction_lights: Default::default(),
+            booster: Default::default(),
+            border_color: Default::default(),
+            card_back_id: Default::default(),
+            collector_number: Default::default(),
+            content_warning: Default::default(),
+            digital: Default::default(),
+            finishes: Default::default(),
+            flavor_name: Default::default(),
+            frame_effects: Default::default(),
+            frame: Default::default(),
+            full_art: Default::default(),
+            games: Default::default(),
+            highres_image: Default::default(),
+            illustration_id: Default::default(),
+            image_status: Default::default(),
+            image_uris: Default::default(),
+            oversized: Default::default(),
+            prices: Default::default(),
+            printed_name: Default::default(),
+            printed_text: Default::default(),
+            printed_type_line: Default::default(),
+            promo: Default::default(),
+            promo_types: Default::default(),
+            purchase_uris: Default::default(),
+            rarity: Default::default(),
+            related_uris: Default::default(),
+            released_at: Default::default(),
+            reprint: Default::default(),
+            scryfall_set_uri: Default::default(),
+            set_name: Default::default(),
+            set_search_uri: Default::default(),
+            set_type: Default::default(),
+            set_uri: Default::default(),
+            set: Default::default(),
+            set_id: Default::default(),
+            story_spotlight: Default::default(),
+            textless: Default::default(),
+            variation: Default::default(),
+            variation_of: Default::default(),
+            security_stamp: Default::default(),
+            watermark: Default::default(),
+            previewed_at: Default::default(),
+            source_uri: Default::default(),
+            source: Default::default(),
+        }
+    }
+}
+
 impl CardObject {
     pub fn is_nonland(&self) -> bool {
         let has_colour: bool = self.colors.0 > 0;
         let back_side_has_colour: bool = match self.card_faces.last() {
-            Some(face) => face.colors.len() > 0,
+            Some(face) => !face.colors.is_empty(),
             None => false,
         };
         let front_side_has_colour: bool = match self.card_faces.first() {
-            Some(face) => face.colors.len() > 0,
+            Some(face) => !face.colors.is_empty(),
             None => false,
         };
         let has_mana_cost: bool = match &self.mana_cost {
-            Some(s) => s.len() > 0,
+            Some(s) => !s.is_empty(),
             None => false,
         };
 
         has_colour || has_mana_cost || front_side_has_colour || back_side_has_colour
     }
 
+    /// match on any name the card goes by: the full name, the name of one of the faces, the flavor name
+    /// or the name printed on a card in another language. compared the way `names::fold` sees them
+    /// so accents, punctuation and spacing don't matter
+    pub fn has_name(&self, name: &str) -> bool {
+        let name = names::fold(name);
+        self.oracle_names()
+            .chain(self.alternate_names())
+            .any(|own| names::fold(own) == name)
+    }
+
+    /// like `has_name`, but only the oracle name and the face names count
+    pub fn has_oracle_name(&self, name: &str) -> bool {
+        let name = names::fold(name);
+        self.oracle_names().any(|own| names::fold(own) == name)
+    }
+
+    /// the oracle name or a face name, where only the case may differ
+    pub fn spelled_as(&self, name: &str) -> bool {
+        let name = name.trim().to_lowercase();
+        self.oracle_names().any(|own| own.to_lowercase() == name)
+    }
+
+    fn oracle_names(&self) -> impl Iterator<Item = &String> {
+        [&self.name]
+            .into_iter()
+            .chain(self.card_faces.iter().map(|face| &face.name))
+    }
+
+    fn alternate_names(&self) -> impl Iterator<Item = &String> {
+        self.flavor_name.iter().chain(&self.printed_name).chain(
+            self.card_faces
+                .iter()
+                .filter_map(|face| face.printed_name.as_ref()),
+        )
+    }
+
+    /// the same for every printing and language of a card, reversible cards only have an oracle_id on their faces
+    pub fn oracle_key(&self) -> Uuid {
+        self.oracle_id.unwrap_or(self.id)
+    }
+
+    /// how many copies of this card a singleton deck can hold, `None` for basic lands and
+    /// cards like Relentless Rats, seven for Seven Dwarves and nine for the Nazgûl
+    pub fn copies_allowed(&self) -> Option<u32> {
+        let text = self.oracle_text.as_deref().unwrap_or_default();
+        if self.type_line.starts_with("Basic") || text.contains("any number of cards named") {
+            None
+        } else if text.contains("up to seven cards named") {
+            Some(7)
+        } else if text.contains("up to nine cards named") {
+            Some(9)
+        } else {
+            Some(1)
+        }
+    }
+
     #[allow(dead_code)]
-    pub fn get_colours<'a>(&'a self) -> &'a Colours {
-        return &self.colors;
+    pub fn get_colours(&self) -> &Colours {
+        &self.colors
     }
 
     #[allow(dead_code)]
-    pub fn get_identity<'a>(&'a self) -> &'a Colours {
-        return &self.color_identity;
+    pub fn get_identity(&self) -> &Colours {
+        &self.color_identity
     }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Default, Clone)]
 ///000WUBRG
-pub struct Colours(u8);
+pub struct Colours(pub u8);
 
 impl<'de> Deserialize<'de> for Colours {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -229,8 +519,8 @@ impl Serialize for Colours {
                 temp_vec.push(*c);
             }
         }
+        // colourless cards are an empty list
         let mut seq = serializer.serialize_seq(Some(length))?;
-        assert!(temp_vec.len() > 0);
 
         for e in temp_vec {
             seq.serialize_element(&e)?;
@@ -346,35 +636,151 @@ pub struct ResponseList {
 }
 
 impl ResponseList {
-    pub fn card_or<'a>(&'a self, query: String) -> SearchResult {
-        let c = self.data.first();
+    /// picks the card `name` meant, an exact name match if there is one and the printing from `set` if that's there.
+    /// when it's still unclear which card was meant the candidates are kept
+    pub fn card_or(&self, query: String, name: &str, set: Option<&str>) -> SearchResult {
+        let exact: Vec<&CardObject> = self.data.iter().filter(|c| c.has_name(name)).collect();
+        let candidates: Vec<&CardObject> = match exact.is_empty() {
+            true => self.data.iter().collect(),
+            false => exact,
+        };
+        let preferred = set
+            .and_then(|set| candidates.iter().find(|c| c.set.eq_ignore_ascii_case(set)))
+            .or(candidates.first());
 
-        if let Some(card_object) = c {
-            if self.total_cards > 1 {
-                return SearchResult::MultipleHits(query, self.total_cards, card_object.clone());
-            };
+        let Some(card_object) = preferred else {
+            return SearchResult::NoHits(query, vec![]);
+        };
+
+        let mut distinct_names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+        distinct_names.sort_unstable();
+        distinct_names.dedup();
 
-            return SearchResult::OneHit(card_object.clone());
+        if self.total_cards > 1 && distinct_names.len() > 1 {
+            // a search for every printing has the same name several times
+            let mut kept: Vec<CardObject> = vec![];
+            for candidate in &candidates {
+                if kept.len() < MAX_CANDIDATES && kept.iter().all(|c| c.name != candidate.name) {
+                    kept.push((*candidate).clone());
+                }
+            }
+            return SearchResult::MultipleHits(
+                query,
+                self.total_cards,
+                (*card_object).clone(),
+                kept,
+            );
         };
 
-        return SearchResult::NoHits(query);
+        SearchResult::OneHit((*card_object).clone())
+    }
+}
+
+/// the body scryfall sends instead of the object that was asked for, for example when a search has no hits
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+pub struct ScryfallError {
+    pub object: String,
+    pub code: String,
+    pub status: u16,
+    pub details: String,
+    #[serde(default)]
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl ScryfallError {
+    pub fn is_not_found(&self) -> bool {
+        self.code == "not_found"
+    }
+}
+
+impl fmt::Display for ScryfallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.details)?;
+        for warning in &self.warnings {
+            write!(f, " ({})", warning)?;
+        }
+        Ok(())
     }
 }
 
+/// every scryfall endpoint answers with either the object asked for or an error object
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum ScryfallResponse<T> {
+    Error(ScryfallError),
+    Data(T),
+}
+
+/// one entry of a `/cards/collection` request
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum CardIdentifier {
+    Printing {
+        set: String,
+        collector_number: String,
+    },
+    Name {
+        name: String,
+    },
+    OracleId {
+        oracle_id: Uuid,
+    },
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+pub struct CollectionResponse {
+    pub object: String,
+    #[serde(default)]
+    pub not_found: Vec<CardIdentifier>,
+    pub data: Vec<CardObject>,
+}
+
+impl CollectionResponse {
+    /// scryfall returns the found cards in the order they were asked for, so they can be matched back up
+    pub fn match_identifiers(self, identifiers: &[CardIdentifier]) -> Vec<Option<CardObject>> {
+        let mut data = self.data.into_iter();
+
+        identifiers
+            .iter()
+            .map(|identifier| match self.not_found.contains(identifier) {
+                true => None,
+                false => data.next(),
+            })
+            .collect()
+    }
+}
+
+/// a list of strings, like the names `/cards/autocomplete` answers with
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Clone)]
+pub struct Catalog {
+    pub object: String,
+    #[serde(default)]
+    pub total_values: u32,
+    pub data: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 pub enum SearchResult {
-    MultipleHits(String, u32, CardObject),
+    /// the query, the number of hits, the card that was picked and the cards it was picked from
+    MultipleHits(String, u32, CardObject, Vec<CardObject>),
     OneHit(CardObject),
-    NoHits(String),
+    /// the query, and names the user might have meant, best guess first
+    NoHits(String, Vec<String>),
+    /// the request for this card failed, it can be asked for again
+    Failed(CardQuery, Arc<RequestError>),
 }
 
 impl SearchResult {
     #[allow(dead_code)]
-    fn get_card_ref<'a>(&'a self) -> Option<&'a CardObject> {
+    fn get_card_ref(&self) -> Option<&CardObject> {
         match self {
-            Self::MultipleHits(_, _, card) => Some(card),
+            Self::MultipleHits(_, _, card, _) => Some(card),
             Self::OneHit(card) => Some(card),
-            Self::NoHits(_) => None,
+            Self::NoHits(_, _) => None,
+            Self::Failed(_, _) => None,
         }
     }
 }
@@ -0,0 +1,482 @@
+use reqwest::{
+    header::{HeaderMap, HeaderValue, RETRY_AFTER},
+    Client, RequestBuilder, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::card::ScryfallError;
+use tokio::{
+    sync::Mutex,
+    time::{sleep, sleep_until, Instant},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay() {
+        let folder = TestFolder::new("land-calc-test-replay");
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        let request = || client.get("/cards/search").query(&[("q", "name:/^Opt$/")]);
+
+        assert!(matches!(
+            client.send(request()).await,
+            Err(RequestError::NotRecorded(_))
+        ));
+
+        save_recording(&folder, &request(), 404, "{}");
+
+        let response = client.send(request()).await.unwrap();
+        // big downloads are never recorded, so there is nothing to replay them from
+        let unrecorded = client.send_unrecorded(request()).await;
+
+        assert!(matches!(unrecorded, Err(RequestError::NotRecorded(_))));
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.text().await.unwrap(), "{}");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_are_spaced() {
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Live).unwrap();
+        let start = Instant::now();
+
+        let mut turns = vec![];
+        for _ in 0..3 {
+            client.wait_turn("/bulk-data").await;
+            turns.push(start.elapsed());
+        }
+
+        assert_eq!(turns, [Duration::ZERO, REQUEST_DELAY, REQUEST_DELAY * 2]);
+
+        // searches only get two turns a second
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Live).unwrap();
+        let start = Instant::now();
+        let mut turns = vec![];
+        for path in ["/cards/search", "/cards/named", "/cards/collection"] {
+            client.wait_turn(path).await;
+            turns.push(start.elapsed());
+        }
+
+        assert_eq!(turns, [Duration::ZERO, SEARCH_DELAY, SEARCH_DELAY * 2]);
+    }
+
+    #[test]
+    fn test_retry_after_and_backoff() {
+        let response = |retry_after: Option<&str>| -> Response {
+            let mut response = http::Response::builder().status(StatusCode::TOO_MANY_REQUESTS);
+            if let Some(value) = retry_after {
+                response = response.header(RETRY_AFTER, value);
+            }
+            response.body("").unwrap().into()
+        };
+
+        assert_eq!(
+            retry_after(&response(Some("7"))),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(retry_after(&response(None)), None);
+        assert_eq!(
+            retry_after(&response(Some("Wed, 21 Oct 2015 07:28:00 GMT"))),
+            None
+        );
+
+        let waits: Vec<Duration> = (0..=MAX_RETRIES).map(backoff).collect();
+        assert_eq!(waits, [1, 2, 4, 8].map(Duration::from_secs),);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limited_then_answered() {
+        let url = stand_in(|_| {
+            vec![
+                b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 5\r\nContent-Length: 0\r\n\r\n"
+                    .to_vec(),
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}".to_vec(),
+            ]
+        })
+        .await;
+        let client = ScryfallClient::new(&url, Mode::Live).unwrap();
+        let start = Instant::now();
+
+        let response = client.send(client.get("/cards/search")).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "{}");
+        // the retry waited for as long as scryfall asked, and so does every request after it
+        assert!(start.elapsed() >= Duration::from_secs(5));
+        assert!(*client.next_request.lock().await >= start + Duration::from_secs(5));
+    }
+}
+
+pub const SCRYFALL_API: &str = "https://api.scryfall.com";
+pub(crate) const APP_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+const CONNECTION: &str = "keep-alive";
+/// scryfall asks for 50-100 milliseconds between requests
+const REQUEST_DELAY: Duration = Duration::from_millis(100);
+/// and allows only 2 a second to the endpoints that search
+const SEARCH_DELAY: Duration = Duration::from_millis(500);
+const SEARCH_PATHS: [&str; 3] = ["/cards/search", "/cards/named", "/cards/collection"];
+const MAX_RETRIES: u32 = 3;
+const BACKOFF: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum RequestError {
+    Http(reqwest::Error),
+    /// scryfall kept answering 429, and asked us to wait this long
+    RateLimited(Duration),
+    /// scryfall kept answering with a server error
+    Unavailable(StatusCode),
+    /// scryfall understood the request, and answered with an error object
+    Scryfall(Box<ScryfallError>),
+    /// reading or writing a recorded response failed
+    Recording(PathBuf, io::Error),
+    /// replay mode, and this request was never recorded
+    NotRecorded(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "request to scryfall failed: {}", e),
+            Self::RateLimited(wait) => write!(
+                f,
+                "scryfall is rate limiting us, wait {} seconds before trying again",
+                wait.as_secs()
+            ),
+            Self::Unavailable(status) => write!(f, "scryfall is unavailable: {}", status),
+            Self::Scryfall(e) => write!(f, "scryfall answered {}", e),
+            Self::Recording(path, e) => write!(f, "recording {}: {}", path.display(), e),
+            Self::NotRecorded(url) => write!(f, "no recorded response for {}", url),
+        }
+    }
+}
+
+impl From<reqwest::Error> for RequestError {
+    fn from(value: reqwest::Error) -> Self {
+        RequestError::Http(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Live,
+    /// talk to the api, and save every response in this folder
+    Record(PathBuf),
+    /// never touch the network, answer from the responses saved in this folder
+    Replay(PathBuf),
+}
+
+/// what gets saved for every request in record mode
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+struct Recording {
+    url: String,
+    status: u16,
+    body: String,
+}
+
+/// one http client shared by every request, so the pacing scryfall asks for holds across all of them
+pub struct ScryfallClient {
+    client: Client,
+    base_url: String,
+    mode: Mode,
+    next_request: Mutex<Instant>,
+    /// the searching endpoints are paced on their own as well
+    next_search: Mutex<Instant>,
+}
+
+impl ScryfallClient {
+    /// `base_url` is where the api lives, `SCRYFALL_API` unless a stand-in server is used
+    pub fn new(base_url: &str, mode: Mode) -> Result<Self, reqwest::Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert("User-Agent", HeaderValue::from_static(APP_USER_AGENT));
+        headers.insert("Connection", HeaderValue::from_static(CONNECTION));
+
+        let client = Client::builder()
+            .default_headers(headers)
+            .https_only(base_url.starts_with("https://"))
+            .build()?;
+
+        Ok(ScryfallClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            mode,
+            next_request: Mutex::new(Instant::now()),
+            next_search: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// a GET request to `path` on the api, like "/bulk-data"
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.client.get(self.api_url(path))
+    }
+
+    /// a POST request to `path` on the api
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(self.api_url(path))
+    }
+
+    /// a GET request to an absolute url, like the `download_uri` of a bulk file
+    pub fn download(&self, url: &str) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+    }
+
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, RequestError> {
+        match &self.mode {
+            Mode::Live => self.send_live(request).await,
+            Mode::Record(folder) => {
+                let (url, path) = recording_path(folder, &request)?;
+                let response = self.send_live(request).await?;
+                let status = response.status();
+                let body = response.text().await?;
+                let recording = Recording {
+                    url,
+                    status: status.as_u16(),
+                    body,
+                };
+
+                fs::create_dir_all(folder)
+                    .and_then(|_| fs::write(&path, serde_json::to_vec_pretty(&recording)?))
+                    .map_err(|e| RequestError::Recording(path.clone(), e))?;
+
+                Ok(replayed_response(recording))
+            }
+            Mode::Replay(folder) => {
+                let (url, path) = recording_path(folder, &request)?;
+                let file = match fs::read(&path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        return Err(RequestError::NotRecorded(url))
+                    }
+                    Err(e) => return Err(RequestError::Recording(path, e)),
+                };
+                let recording: Recording = serde_json::from_slice(&file)
+                    .map_err(|e| RequestError::Recording(path, e.into()))?;
+
+                Ok(replayed_response(recording))
+            }
+        }
+    }
+
+    /// sends a request whose answer is too big to keep in a recording, like a bulk file download,
+    /// so the body can be streamed. recording only passes it through, replaying can't answer it
+    pub async fn send_unrecorded(&self, request: RequestBuilder) -> Result<Response, RequestError> {
+        match &self.mode {
+            Mode::Live | Mode::Record(_) => self.send_live(request).await,
+            Mode::Replay(folder) => Err(RequestError::NotRecorded(
+                recording_path(folder, &request)?.0,
+            )),
+        }
+    }
+
+    /// sends the request in its turn, retrying 429, 5xx and timeouts with backoff
+    async fn send_live(&self, request: RequestBuilder) -> Result<Response, RequestError> {
+        let mut attempt = 0;
+        let path = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .map(|r| r.url().path().to_string())
+            .unwrap_or_default();
+
+        loop {
+            let this_try = request
+                .try_clone()
+                .expect("scryfall requests don't stream their body");
+            self.wait_turn(&path).await;
+
+            let wait = backoff(attempt);
+            let (error, wait) = match this_try.send().await {
+                Ok(response) => match response.status() {
+                    StatusCode::TOO_MANY_REQUESTS => {
+                        let wait = retry_after(&response).unwrap_or(wait);
+                        // everyone waits, not just this request
+                        self.pause(wait).await;
+                        (RequestError::RateLimited(wait), Duration::ZERO)
+                    }
+                    StatusCode::SERVICE_UNAVAILABLE => {
+                        let wait = retry_after(&response).unwrap_or(wait);
+                        (RequestError::Unavailable(response.status()), wait)
+                    }
+                    status if status.is_server_error() => (RequestError::Unavailable(status), wait),
+                    _ => return Ok(response),
+                },
+                Err(e) if e.is_timeout() || e.is_connect() => (RequestError::Http(e), wait),
+                Err(e) => return Err(e.into()),
+            };
+
+            if attempt == MAX_RETRIES {
+                return Err(error);
+            }
+            attempt += 1;
+            sleep(wait).await;
+        }
+    }
+
+    /// waits until a request to `path` may be sent
+    async fn wait_turn(&self, path: &str) {
+        if SEARCH_PATHS.iter().any(|search| path.ends_with(search)) {
+            sleep_until(take_turn(&self.next_search, SEARCH_DELAY).await).await;
+        }
+        sleep_until(take_turn(&self.next_request, REQUEST_DELAY).await).await;
+    }
+
+    async fn pause(&self, wait: Duration) {
+        for next in [&self.next_request, &self.next_search] {
+            let mut next = next.lock().await;
+            *next = (*next).max(Instant::now() + wait);
+        }
+    }
+}
+
+/// the earliest turn free on `next`, the one after it is `delay` later
+async fn take_turn(next: &Mutex<Instant>, delay: Duration) -> Instant {
+    let mut next = next.lock().await;
+    let turn = (*next).max(Instant::now());
+    *next = turn + delay;
+    turn
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF * 2u32.pow(attempt)
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds: u64 = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// every request gets its own file, named after a hash of everything that makes it unique except the host
+fn recording_path(
+    folder: &Path,
+    request: &RequestBuilder,
+) -> Result<(String, PathBuf), RequestError> {
+    let request = request
+        .try_clone()
+        .expect("scryfall requests don't stream their body")
+        .build()?;
+    let url = request.url();
+    let body = request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .unwrap_or_default();
+
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let key = [
+        request.method().as_str().as_bytes(),
+        url.path().as_bytes(),
+        url.query().unwrap_or_default().as_bytes(),
+        body,
+    ];
+    // FNV-1a, recordings have to keep their names between rust versions
+    for byte in key.concat() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    let file = format!(
+        "{}-{:016x}.json",
+        request.method().as_str().to_lowercase(),
+        hash
+    );
+    Ok((url.to_string(), folder.join(file)))
+}
+
+/// a folder of recordings for one test of this run, removed when the test ends, by a panic too
+#[cfg(test)]
+pub struct TestFolder(PathBuf);
+
+#[cfg(test)]
+impl TestFolder {
+    /// the process id keeps an earlier run's leftovers out of this one
+    pub fn new(name: &str) -> Self {
+        let folder = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        TestFolder(folder)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestFolder {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestFolder {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// a stand-in for scryfall on localhost that gives the responses in order, one per request.
+/// `responses` gets the url it listens on, for responses that link back to it
+#[cfg(test)]
+pub async fn stand_in(responses: impl FnOnce(&str) -> Vec<Vec<u8>>) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let mut responses = responses(&url).into_iter();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            // every request is a GET, it ends with its headers
+            while let Ok(read @ 1..) = stream.read(&mut buffer).await {
+                request.extend_from_slice(&buffer[..read]);
+                if !request.ends_with(b"\r\n\r\n") {
+                    continue;
+                }
+                request.clear();
+                let Some(response) = responses.next() else {
+                    return;
+                };
+                if stream.write_all(&response).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    url
+}
+
+/// saves `body` as scryfall's answer to `request`, for tests that replay it
+#[cfg(test)]
+pub fn save_recording(folder: &Path, request: &RequestBuilder, status: u16, body: &str) {
+    let (url, path) = recording_path(folder, request).unwrap();
+    let recording = Recording {
+        url,
+        status,
+        body: body.to_string(),
+    };
+    fs::create_dir_all(folder).unwrap();
+    fs::write(&path, serde_json::to_vec(&recording).unwrap()).unwrap();
+}
+
+fn replayed_response(recording: Recording) -> Response {
+    let status = StatusCode::from_u16(recording.status).unwrap_or(StatusCode::OK);
+    http::Response::builder()
+        .status(status)
+        .body(recording.body)
+        .expect("status and body are valid")
+        .into()
+}
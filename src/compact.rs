@@ -0,0 +1,396 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+use serde_json::Number;
+use uuid::Uuid;
+
+use crate::card::{CardFace, CardObject, Colours};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card::ResponseList, client::TestFolder};
+
+    #[test]
+    fn test_index_round_trip() {
+        let json_str = fs::read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let cards: Vec<CompactCard> = response.data.iter().map(CompactCard::from).collect();
+        let folder = TestFolder::new("land-calc-test-index");
+        let path = folder.join("cards.index");
+
+        write_index(&path, &cards).unwrap();
+        let read = read_index(&path).unwrap();
+
+        assert_eq!(read, cards);
+        let tasigur = CardObject::from(&read[0]);
+        assert_eq!(tasigur.name, "Tasigur, the Golden Fang");
+        assert_eq!(tasigur.cmc.as_f64(), Some(6.0));
+        assert_eq!(tasigur.legalities["legacy"], "legal");
+        assert!(tasigur.is_nonland());
+    }
+
+    #[test]
+    fn test_truncated_index() {
+        let folder = TestFolder::new("land-calc-test-truncated");
+        let path = folder.join("truncated.index");
+        fs::write(&path, [MAGIC.as_slice(), &[VERSION as u8]].concat()).unwrap();
+
+        let read = read_index(&path);
+
+        let mut huge_count = Writer(MAGIC.to_vec());
+        huge_count.u32(VERSION);
+        // no formats and no legalities
+        huge_count.u32(0);
+        huge_count.u32(0);
+        huge_count.u32(u32::MAX);
+        fs::write(&path, huge_count.0).unwrap();
+        let read_huge = read_index(&path);
+
+        assert!(read.is_err());
+        assert!(read_huge.is_err());
+    }
+}
+
+const MAGIC: &[u8; 4] = b"LCIX";
+/// bump this whenever the layout below changes, older index files are rebuilt
+const VERSION: u32 = 3;
+/// what a byte in the legalities table stands for
+const NOT_IN_FORMAT: u8 = u8::MAX;
+
+/// the part of a card the calculator and the offline lookups need, a fraction of what scryfall sends
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CompactCard {
+    pub id: Uuid,
+    pub oracle_id: Option<Uuid>,
+    pub lang: String,
+    pub name: String,
+    pub flavor_name: Option<String>,
+    /// the name on cards in other languages
+    pub printed_name: Option<String>,
+    pub set: String,
+    pub collector_number: String,
+    pub layout: String,
+    pub faces: Vec<CompactFace>,
+    pub cmc: f64,
+    pub mana_cost: Option<String>,
+    pub type_line: String,
+    pub colors: u8,
+    pub color_identity: u8,
+    pub produced_mana: Vec<char>,
+    pub keywords: Vec<String>,
+    pub oracle_text: Option<String>,
+    pub legalities: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct CompactFace {
+    pub name: String,
+    pub printed_name: Option<String>,
+    pub mana_cost: String,
+    pub type_line: Option<String>,
+    pub oracle_text: Option<String>,
+    pub colors: Vec<char>,
+}
+
+impl CompactCard {
+    /// the same key as `CardObject::oracle_key`
+    pub fn oracle_key(&self) -> Uuid {
+        self.oracle_id.unwrap_or(self.id)
+    }
+}
+
+impl From<&CardObject> for CompactCard {
+    fn from(card: &CardObject) -> Self {
+        CompactCard {
+            id: card.id,
+            oracle_id: card.oracle_id,
+            lang: card.lang.clone(),
+            name: card.name.clone(),
+            flavor_name: card.flavor_name.clone(),
+            printed_name: card.printed_name.clone(),
+            set: card.set.clone(),
+            collector_number: card.collector_number.clone(),
+            layout: card.layout.clone(),
+            faces: card
+                .card_faces
+                .iter()
+                .map(|face| CompactFace {
+                    name: face.name.clone(),
+                    printed_name: face.printed_name.clone(),
+                    mana_cost: face.mana_cost.clone(),
+                    type_line: face.type_line.clone(),
+                    oracle_text: face.oracle_text.clone(),
+                    colors: face.colors.clone(),
+                })
+                .collect(),
+            cmc: card.cmc.as_f64().unwrap_or_default(),
+            mana_cost: card.mana_cost.clone(),
+            type_line: card.type_line.clone(),
+            colors: card.colors.0,
+            color_identity: card.color_identity.0,
+            produced_mana: card.produced_mana.clone(),
+            keywords: card.keywords.clone(),
+            oracle_text: card.oracle_text.clone(),
+            legalities: card.legalities.clone(),
+        }
+    }
+}
+
+/// everything the index didn't keep is left empty
+impl From<&CompactCard> for CardObject {
+    fn from(card: &CompactCard) -> Self {
+        CardObject {
+            id: card.id,
+            oracle_id: card.oracle_id,
+            lang: card.lang.clone(),
+            name: card.name.clone(),
+            flavor_name: card.flavor_name.clone(),
+            printed_name: card.printed_name.clone(),
+            set: card.set.clone(),
+            collector_number: card.collector_number.clone(),
+            layout: card.layout.clone(),
+            card_faces: card
+                .faces
+                .iter()
+                .map(|face| CardFace {
+                    name: face.name.clone(),
+                    printed_name: face.printed_name.clone(),
+                    mana_cost: face.mana_cost.clone(),
+                    type_line: face.type_line.clone(),
+                    oracle_text: face.oracle_text.clone(),
+                    colors: face.colors.clone(),
+                    object: "card_face".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            cmc: Number::from_f64(card.cmc).unwrap_or(Number::from(0)),
+            mana_cost: card.mana_cost.clone(),
+            type_line: card.type_line.clone(),
+            colors: Colours(card.colors),
+            color_identity: Colours(card.color_identity),
+            produced_mana: card.produced_mana.clone(),
+            keywords: card.keywords.clone(),
+            oracle_text: card.oracle_text.clone(),
+            legalities: card.legalities.clone(),
+            object: "card".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// saves the cards in a little-endian, length-prefixed layout.
+/// format names and legality words are written once up front, each card only keeps one byte per format
+pub fn write_index(path: &Path, cards: &[CompactCard]) -> io::Result<()> {
+    let mut formats: Vec<&str> = cards
+        .iter()
+        .flat_map(|c| c.legalities.keys().map(String::as_str))
+        .collect();
+    formats.sort_unstable();
+    formats.dedup();
+    let mut statuses: Vec<&str> = cards
+        .iter()
+        .flat_map(|c| c.legalities.values().map(String::as_str))
+        .collect();
+    statuses.sort_unstable();
+    statuses.dedup();
+    if statuses.len() >= NOT_IN_FORMAT as usize {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "too many different legalities",
+        ));
+    }
+
+    let mut out = Writer(Vec::with_capacity(cards.len() * 512));
+    out.0.extend_from_slice(MAGIC);
+    out.u32(VERSION);
+    out.strings(&formats);
+    out.strings(&statuses);
+    out.u32(cards.len() as u32);
+
+    for card in cards {
+        out.uuid(card.id);
+        out.option(card.oracle_id, Writer::uuid);
+        out.str(&card.lang);
+        out.str(&card.name);
+        out.option(card.flavor_name.as_deref(), Writer::str);
+        out.option(card.printed_name.as_deref(), Writer::str);
+        out.str(&card.set);
+        out.str(&card.collector_number);
+        out.str(&card.layout);
+        out.u32(card.faces.len() as u32);
+        for face in &card.faces {
+            out.str(&face.name);
+            out.option(face.printed_name.as_deref(), Writer::str);
+            out.str(&face.mana_cost);
+            out.option(face.type_line.as_deref(), Writer::str);
+            out.option(face.oracle_text.as_deref(), Writer::str);
+            out.str(&face.colors.iter().collect::<String>());
+        }
+        out.0.extend_from_slice(&card.cmc.to_le_bytes());
+        out.option(card.mana_cost.as_deref(), Writer::str);
+        out.str(&card.type_line);
+        out.0.push(card.colors);
+        out.0.push(card.color_identity);
+        out.str(&card.produced_mana.iter().collect::<String>());
+        out.strings(&card.keywords);
+        out.option(card.oracle_text.as_deref(), Writer::str);
+        for format in &formats {
+            let status = card
+                .legalities
+                .get(*format)
+                .and_then(|status| statuses.iter().position(|s| s == status))
+                .map_or(NOT_IN_FORMAT, |i| i as u8);
+            out.0.push(status);
+        }
+    }
+
+    fs::write(path, out.0)
+}
+
+/// reads back what `write_index` wrote, a file from another version is an `InvalidData` error
+pub fn read_index(path: &Path) -> io::Result<Vec<CompactCard>> {
+    let bytes = fs::read(path)?;
+    let mut input = Reader(&bytes);
+
+    if input.take(MAGIC.len())? != MAGIC || input.u32()? != VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "not a card index of this version",
+        ));
+    }
+    let formats = input.strings()?;
+    let statuses = input.strings()?;
+    let count = input.u32()? as usize;
+
+    // the count comes from the file, a corrupt one mustn't decide how much memory is asked for
+    let mut cards = Vec::new();
+    for _ in 0..count {
+        let mut card = CompactCard {
+            id: input.uuid()?,
+            oracle_id: input.option(Reader::uuid)?,
+            lang: input.string()?,
+            name: input.string()?,
+            flavor_name: input.option(Reader::string)?,
+            printed_name: input.option(Reader::string)?,
+            set: input.string()?,
+            collector_number: input.string()?,
+            layout: input.string()?,
+            ..Default::default()
+        };
+        for _ in 0..input.u32()? {
+            card.faces.push(CompactFace {
+                name: input.string()?,
+                printed_name: input.option(Reader::string)?,
+                mana_cost: input.string()?,
+                type_line: input.option(Reader::string)?,
+                oracle_text: input.option(Reader::string)?,
+                colors: input.string()?.chars().collect(),
+            });
+        }
+        card.cmc = f64::from_le_bytes(input.take(8)?.try_into().expect("took 8 bytes"));
+        card.mana_cost = input.option(Reader::string)?;
+        card.type_line = input.string()?;
+        card.colors = input.u8()?;
+        card.color_identity = input.u8()?;
+        card.produced_mana = input.string()?.chars().collect();
+        card.keywords = input.strings()?;
+        card.oracle_text = input.option(Reader::string)?;
+        for format in &formats {
+            let status = input.u8()?;
+            if status != NOT_IN_FORMAT {
+                let status = statuses
+                    .get(status as usize)
+                    .ok_or(ErrorKind::InvalidData)?;
+                card.legalities.insert(format.clone(), status.clone());
+            }
+        }
+        cards.push(card);
+    }
+
+    Ok(cards)
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn strings(&mut self, values: &[impl AsRef<str>]) {
+        self.u32(values.len() as u32);
+        for value in values {
+            self.str(value.as_ref());
+        }
+    }
+
+    fn uuid(&mut self, value: Uuid) {
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn option<T>(&mut self, value: Option<T>, write: impl Fn(&mut Self, T)) {
+        match value {
+            Some(value) => {
+                self.0.push(1);
+                write(self, value);
+            }
+            None => self.0.push(0),
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.take(4)?.try_into().expect("took 4 bytes"),
+        ))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn strings(&mut self) -> io::Result<Vec<String>> {
+        (0..self.u32()?).map(|_| self.string()).collect()
+    }
+
+    fn uuid(&mut self) -> io::Result<Uuid> {
+        Ok(Uuid::from_bytes(
+            self.take(16)?.try_into().expect("took 16 bytes"),
+        ))
+    }
+
+    fn option<T>(&mut self, read: impl Fn(&mut Self) -> io::Result<T>) -> io::Result<Option<T>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => read(self).map(Some),
+        }
+    }
+}
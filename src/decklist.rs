@@ -0,0 +1,737 @@
+use std::{collections::HashMap, fmt, io, str::FromStr};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_line() {
+        let list =
+            parse("1 Kellan, Daring Traveler // Journey On (LCI) 231\n3 Forest (ELD) 266").unwrap();
+
+        assert_eq!(list.format, DeckFormat::Plain);
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(
+            list.entries[0].name,
+            "Kellan, Daring Traveler // Journey On"
+        );
+        assert_eq!(list.entries[0].set.as_deref(), Some("LCI"));
+        assert_eq!(list.entries[0].collector_number.as_deref(), Some("231"));
+        assert_eq!(list.entries[1].amount, 3);
+        assert_eq!(list.entries[1].line, 2);
+    }
+
+    #[test]
+    fn test_moxfield_line() {
+        let list =
+            parse("1x Sol Ring (cmr) 472 *F* [Ramp]\n1x B.F.M. (Big Furry Monster)").unwrap();
+
+        assert_eq!(list.format, DeckFormat::Moxfield);
+        assert_eq!(list.entries[0].name, "Sol Ring");
+        assert_eq!(list.entries[0].set.as_deref(), Some("cmr"));
+        assert_eq!(list.entries[0].collector_number.as_deref(), Some("472"));
+        assert_eq!(list.entries[1].name, "B.F.M. (Big Furry Monster)");
+        assert_eq!(list.entries[1].set, None);
+    }
+
+    #[test]
+    fn test_plain_list_with_x_quantities() {
+        assert_eq!(
+            parse("4x Opt\n2x Duress").unwrap().format,
+            DeckFormat::Plain
+        );
+        assert_eq!(parse("4x Opt (XLN) 65").unwrap().format, DeckFormat::Plain);
+        assert_eq!(
+            parse("4 Opt (xln) 65").unwrap().format,
+            DeckFormat::Moxfield
+        );
+    }
+
+    #[test]
+    fn test_arena_sections() {
+        let text = "About\nName Test\n\nCommander\n1 Kellan, Daring Traveler (LCI) 231\n\nCompanion\n1 Jegantha, the Wellspring (IKO) 222\n\nDeck\n1 Sol Ring (CMR) 472\n\nSideboard\n1 Duress (M19) 94";
+        let list = parse(text).unwrap();
+
+        assert_eq!(list.format, DeckFormat::Arena);
+        let zones: Vec<Zone> = list.entries.iter().map(|e| e.zone).collect();
+        assert_eq!(
+            zones,
+            [
+                Zone::Commander,
+                Zone::Companion,
+                Zone::Main,
+                Zone::Sideboard
+            ]
+        );
+        assert_eq!(list.playables().count(), 2);
+    }
+
+    #[test]
+    fn test_mtgo_dek() {
+        let text = r#"<?xml version="1.0" encoding="utf-8"?>
+<Deck xmlns:xsd="http://www.w3.org/2001/XMLSchema">
+  <NetDeckID>0</NetDeckID>
+  <Cards CatID="1" Quantity="4" Sideboard="false" Name="Lim-D&#251;l&apos;s Vault" Annotation="0" />
+  <Cards CatID="2" Quantity="2" Sideboard="true" Name="Duress" Annotation="0" />
+</Deck>"#;
+        let list = parse(text).unwrap();
+
+        assert_eq!(list.format, DeckFormat::Mtgo);
+        assert_eq!(list.entries[0].name, "Lim-Dûl's Vault");
+        assert_eq!(list.entries[0].amount, 4);
+        assert_eq!(list.entries[1].zone, Zone::Sideboard);
+        assert_eq!(list.entries[1].line, 5);
+    }
+
+    #[test]
+    fn test_cockatrice_cod() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<cockatrice_deck version="1">
+    <deckname>test</deckname>
+    <zone name="main">
+        <card number="4" name="Opt"/>
+    </zone>
+    <zone name="side">
+        <card number="2" name="Duress" setShortName="M19" collectorNumber="94"/>
+    </zone>
+    <zone name="tokens">
+        <card number="1" name="Treasure"/>
+    </zone>
+</cockatrice_deck>"#;
+        let list = parse(text).unwrap();
+
+        assert_eq!(list.format, DeckFormat::Cockatrice);
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(list.entries[0].zone, Zone::Main);
+        assert_eq!(list.entries[1].zone, Zone::Sideboard);
+        assert_eq!(list.entries[1].set.as_deref(), Some("M19"));
+    }
+
+    #[test]
+    fn test_bad_line() {
+        let list = parse("1 Opt\n  0x Opt\n4x\n2 Duress").unwrap();
+
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(list.entries[1].name, "Duress");
+        assert_eq!(
+            list.diagnostics[0],
+            Diagnostic {
+                line: 2,
+                column: 3,
+                text: "0x".to_string(),
+                reason: "expected a number of copies",
+            }
+        );
+        assert_eq!(list.diagnostics[1].line, 3);
+        assert_eq!(list.diagnostics[1].reason, "missing card name");
+    }
+
+    #[test]
+    fn test_quantities_and_comments() {
+        let text = "// Burn\n4x Lightning Bolt\nx2 Fire // Ice\nOpt\n# sideboard\nSB: 2 Duress\nsb:Pyroblast";
+        let list = parse(text).unwrap();
+
+        let read: Vec<(u32, &str, Zone)> = list
+            .entries
+            .iter()
+            .map(|e| (e.amount, e.name.as_str(), e.zone))
+            .collect();
+        assert_eq!(
+            read,
+            [
+                (4, "Lightning Bolt", Zone::Main),
+                (2, "Fire // Ice", Zone::Main),
+                (1, "Opt", Zone::Main),
+                (2, "Duress", Zone::Sideboard),
+                (1, "Pyroblast", Zone::Sideboard),
+            ]
+        );
+        assert!(list.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_bad_xml_card() {
+        let text = r#"<Deck>
+  <Cards Quantity="4" Name="Opt" />
+  <Cards Quantity="lots" Name="Duress" />
+  <Cards Quantity="1" Name="Shock"
+</Deck>"#;
+        let list = parse(text).unwrap();
+
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.diagnostics.len(), 2);
+        assert_eq!(list.diagnostics[0].line, 3);
+        assert_eq!(list.diagnostics[0].column, 3);
+        assert_eq!(list.diagnostics[1].reason, "unclosed xml tag");
+    }
+
+    #[test]
+    fn test_name_starting_with_a_number() {
+        let list = parse("1996 World Champion\n4\tOpt\n1000x Opt\n0 Opt").unwrap();
+
+        let read: Vec<(u32, &str)> = list
+            .entries
+            .iter()
+            .map(|e| (e.amount, e.name.as_str()))
+            .collect();
+        assert_eq!(read, [(1, "1996 World Champion"), (4, "Opt")]);
+        assert_eq!(
+            list.diagnostics[0].reason,
+            "too many copies, read as part of the card name"
+        );
+        assert_eq!(list.diagnostics[1].line, 3);
+        assert_eq!(list.diagnostics[1].reason, "expected a number of copies");
+        assert_eq!(list.diagnostics[2].line, 4);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeckFormat {
+    /// "N Name (SET) num", one card per line, like list.txt
+    Plain,
+    /// MTG Arena export, with "Deck"/"Sideboard" headers
+    Arena,
+    /// MTGO .dek xml
+    Mtgo,
+    /// Moxfield/Archidekt text export, "Nx Name (set) num *F* [Category]"
+    Moxfield,
+    /// Cockatrice .cod xml
+    Cockatrice,
+}
+
+impl fmt::Display for DeckFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Plain => "plain text",
+            Self::Arena => "MTG Arena",
+            Self::Mtgo => "MTGO .dek",
+            Self::Moxfield => "Moxfield/Archidekt",
+            Self::Cockatrice => "Cockatrice .cod",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for DeckFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "arena" => Ok(Self::Arena),
+            "mtgo" => Ok(Self::Mtgo),
+            "moxfield" => Ok(Self::Moxfield),
+            "cockatrice" => Ok(Self::Cockatrice),
+            _ => Err(format!(
+                "{} is not a decklist format, try plain, arena, mtgo, moxfield or cockatrice",
+                s
+            )),
+        }
+    }
+}
+
+impl DeckFormat {
+    pub fn detect(text: &str) -> Option<Self> {
+        let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+
+        if trimmed.starts_with('<') {
+            if trimmed.contains("<cockatrice_deck") {
+                return Some(Self::Cockatrice);
+            }
+            if trimmed.contains("<Deck") {
+                return Some(Self::Mtgo);
+            }
+            return None;
+        }
+
+        let lines = || {
+            text.lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !is_comment(l))
+        };
+
+        if lines().any(is_moxfield_line) {
+            return Some(Self::Moxfield);
+        }
+        if lines().any(|l| section_header(l).is_some()) {
+            return Some(Self::Arena);
+        }
+        Some(Self::Plain)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Zone {
+    Main,
+    Commander,
+    Companion,
+    Sideboard,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeckEntry {
+    /// 1-based line in the source file
+    pub line: usize,
+    pub amount: u32,
+    pub name: String,
+    pub set: Option<String>,
+    pub collector_number: Option<String>,
+    pub zone: Zone,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Decklist {
+    pub format: DeckFormat,
+    pub entries: Vec<DeckEntry>,
+    /// lines that could not be read, everything else in the file still is
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Decklist {
+    pub fn has_zone(&self, zone: Zone) -> bool {
+        self.entries.iter().any(|e| e.zone == zone)
+    }
+
+    /// every entry the game starts with in the deck or the command zone,
+    /// so neither the sideboard nor a companion, which waits outside the game
+    pub fn playables(&self) -> impl Iterator<Item = &DeckEntry> {
+        self.entries
+            .iter()
+            .filter(|e| !matches!(e.zone, Zone::Sideboard | Zone::Companion))
+    }
+}
+
+/// a part of the decklist that was skipped or read differently than it was written, and why
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    /// 1-based line in the source file
+    pub line: usize,
+    /// 1-based column, in characters, where `text` starts
+    pub column: usize,
+    pub text: String,
+    pub reason: &'static str,
+}
+
+impl Diagnostic {
+    /// points at `text`, which has to be a slice of `source_line`
+    fn at(line: usize, source_line: &str, text: &str, reason: &'static str) -> Self {
+        let offset = text.as_ptr() as usize - source_line.as_ptr() as usize;
+
+        Diagnostic {
+            line,
+            column: source_line[..offset].chars().count() + 1,
+            text: text.to_string(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {} in '{}'",
+            self.line, self.column, self.reason, self.text
+        )
+    }
+}
+
+/// problems with the whole file, problems with single lines are `Diagnostic`s
+#[derive(Debug)]
+pub enum DecklistError {
+    Io(io::Error),
+    UnknownFormat,
+}
+
+impl fmt::Display for DecklistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "could not read the decklist: {}", e),
+            Self::UnknownFormat => f.write_str("could not recognise the decklist format"),
+        }
+    }
+}
+
+impl From<io::Error> for DecklistError {
+    fn from(value: io::Error) -> Self {
+        DecklistError::Io(value)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Decklist, DecklistError> {
+    let format = DeckFormat::detect(text).ok_or(DecklistError::UnknownFormat)?;
+
+    let mut diagnostics = vec![];
+
+    let entries = match format {
+        DeckFormat::Plain | DeckFormat::Arena | DeckFormat::Moxfield => {
+            parse_text(text, &mut diagnostics)
+        }
+        DeckFormat::Mtgo => parse_mtgo(text, &mut diagnostics),
+        DeckFormat::Cockatrice => parse_cockatrice(text, &mut diagnostics),
+    };
+    // the xml scanner reports unclosed tags before the cards are read
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+
+    Ok(Decklist {
+        format,
+        entries,
+        diagnostics,
+    })
+}
+
+/// recognises the "Deck", "Commander", "SIDEBOARD:" etc. lines arena and moxfield put above each block,
+/// blocks that aren't part of the deck (like "About" or "Maybeboard") map to `Some(None)`
+fn section_header(line: &str) -> Option<Option<Zone>> {
+    let header = line.trim_end_matches(':').trim().to_lowercase();
+
+    match header.as_str() {
+        "deck" | "main" | "mainboard" => Some(Some(Zone::Main)),
+        "commander" | "commanders" => Some(Some(Zone::Commander)),
+        "companion" => Some(Some(Zone::Companion)),
+        "sideboard" => Some(Some(Zone::Sideboard)),
+        "about" | "maybeboard" | "considering" => Some(None),
+        _ => None,
+    }
+}
+
+/// "*F*", "[Category]" and "^Tag^" at the end, or a set code in lowercase.
+/// "4x" is just as common in plain lists, so it doesn't count
+fn is_moxfield_line(line: &str) -> bool {
+    let lowercase_set = split_printing(line).1.is_some_and(|set| {
+        set.chars().any(|c| c.is_ascii_lowercase()) && !set.chars().any(|c| c.is_ascii_uppercase())
+    });
+
+    lowercase_set || line.ends_with(['*', ']', '^'])
+}
+
+/// "// Burn" and "# sideboard plan" lines, split cards only have their "//" between two names
+fn is_comment(line: &str) -> bool {
+    line.starts_with("//") || line.starts_with('#')
+}
+
+/// no deck runs more copies of a card, a bigger number is the start of a name like "1996 World Champion"
+const MAX_COPIES: u32 = 100;
+
+/// splits "4 Opt", "4x Opt" and "x4 Opt" into the quantity as written, its digits and the rest.
+/// `None` when the line doesn't start with one
+fn split_quantity(line: &str) -> Option<(&str, &str, &str)> {
+    let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let digits = first
+        .strip_suffix(['x', 'X'])
+        .or_else(|| first.strip_prefix(['x', 'X']))
+        .unwrap_or(first);
+
+    let is_quantity = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+    is_quantity.then_some((first, digits, rest))
+}
+
+fn parse_text(text: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<DeckEntry> {
+    let mut entries = vec![];
+    let mut zone = Some(Zone::Main);
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || is_comment(line) {
+            continue;
+        }
+        if let Some(header) = section_header(line) {
+            zone = header;
+            continue;
+        }
+        // "Name ..." lines live under the arena "About" header, maybeboards aren't part of the deck
+        let Some(mut zone) = zone else { continue };
+
+        let mut skip = |text, reason| diagnostics.push(Diagnostic::at(i + 1, raw, text, reason));
+
+        let mut line = line;
+        // the old MTGO and apprentice way of listing sideboard cards
+        if line
+            .get(..3)
+            .is_some_and(|sb| sb.eq_ignore_ascii_case("sb:"))
+        {
+            line = line[3..].trim_start();
+            zone = Zone::Sideboard;
+        }
+
+        // a card without a quantity in front of it is a single copy
+        let (amount, rest) = match split_quantity(line) {
+            Some((written, digits, rest)) => match digits.parse::<u32>() {
+                Ok(amount) if amount > 0 && amount <= MAX_COPIES => (amount, rest),
+                // "1996 World Champion" is a single card, not 1996 of "World Champion"
+                Ok(amount) if amount > 0 && written == digits && !rest.trim().is_empty() => {
+                    skip(written, "too many copies, read as part of the card name");
+                    (1, line)
+                }
+                _ => {
+                    skip(written, "expected a number of copies");
+                    continue;
+                }
+            },
+            None => (1, line),
+        };
+        let (name, set, collector_number) = split_printing(strip_annotations(rest.trim()));
+
+        if name.is_empty() {
+            skip(line, "missing card name");
+            continue;
+        }
+
+        entries.push(DeckEntry {
+            line: i + 1,
+            amount,
+            name,
+            set,
+            collector_number,
+            zone,
+        });
+    }
+
+    entries
+}
+
+/// removes the trailing "*F*", "[Category]" and "^Tag^" markers moxfield and archidekt add
+fn strip_annotations(mut s: &str) -> &str {
+    loop {
+        let trimmed = s.trim_end();
+        let stripped = [('*', '*'), ('[', ']'), ('^', '^')]
+            .iter()
+            .find_map(|(open, close)| {
+                let without_close = trimmed.strip_suffix(*close)?;
+                let start = without_close.rfind(*open)?;
+                Some(&trimmed[..start])
+            });
+
+        match stripped {
+            Some(rest) => s = rest,
+            None => return trimmed,
+        }
+    }
+}
+
+/// splits "Name (SET) num" into its parts, the set and number are optional
+fn split_printing(s: &str) -> (String, Option<String>, Option<String>) {
+    let clean_name = |n: &str| n.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    if let Some(open) = s.rfind(" (") {
+        if let Some((set, number)) = s[open + 2..].split_once(')') {
+            let is_set_code =
+                (2..=6).contains(&set.len()) && set.chars().all(|c| c.is_ascii_alphanumeric());
+            let number = number.trim();
+
+            if is_set_code && !number.contains(' ') {
+                let number = (!number.is_empty()).then(|| number.to_string());
+                return (clean_name(&s[..open]), Some(set.to_string()), number);
+            }
+        }
+    }
+
+    (clean_name(s), None, None)
+}
+
+struct XmlTag<'a> {
+    line: usize,
+    /// the whole line the tag starts on, and the tag itself from its '<'
+    source_line: &'a str,
+    source: &'a str,
+    name: &'a str,
+    closing: bool,
+    attributes: HashMap<&'a str, String>,
+}
+
+/// a tiny tag scanner, decklist files only need element names and attributes.
+/// a tag that runs into the next '<' is reported and skipped, the scan goes on after it
+fn xml_tags<'a>(text: &'a str, diagnostics: &mut Vec<Diagnostic>) -> Vec<XmlTag<'a>> {
+    let mut tags = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let offset = text.len() - rest.len() + start;
+        let line = text[..offset].matches('\n').count() + 1;
+        let line_start = text[..offset].rfind('\n').map_or(0, |n| n + 1);
+        let source_line = text[line_start..].lines().next().unwrap_or_default();
+        let next_tag = rest[start + 1..].find('<').map_or(usize::MAX, |n| n + 1);
+        let Some(end) = rest[start..].find('>').filter(|end| *end < next_tag) else {
+            let unclosed = rest[start..].lines().next().unwrap_or_default();
+            diagnostics.push(Diagnostic::at(
+                line,
+                source_line,
+                unclosed,
+                "unclosed xml tag",
+            ));
+            rest = &rest[start + 1..];
+            continue;
+        };
+        let source = &rest[start..start + end + 1];
+        let body = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        if body.starts_with('?') || body.starts_with('!') {
+            continue;
+        }
+
+        let closing = body.starts_with('/');
+        let body = body.trim_start_matches('/').trim_end_matches('/');
+        let name_end = body.find(char::is_whitespace).unwrap_or(body.len());
+        let mut attributes = HashMap::new();
+        let mut attrs = &body[name_end..];
+
+        while let Some(eq) = attrs.find('=') {
+            let key = attrs[..eq].trim();
+            let value = attrs[eq + 1..].trim_start();
+            let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'');
+            let Some(quote) = quote else { break };
+            let Some(value_end) = value[1..].find(quote) else {
+                break;
+            };
+
+            attributes.insert(key, unescape_xml(&value[1..value_end + 1]));
+            attrs = &value[value_end + 2..];
+        }
+
+        tags.push(XmlTag {
+            line,
+            source_line,
+            source,
+            name: &body[..name_end],
+            closing,
+            attributes,
+        });
+    }
+
+    tags
+}
+
+fn unescape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else { break };
+        let entity = &rest[1..semi];
+
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+impl XmlTag<'_> {
+    fn diagnostic(&self, reason: &'static str) -> Diagnostic {
+        // a tag spread over several lines is shown up to the end of its first one
+        let text = self.source.lines().next().unwrap_or_default();
+        Diagnostic::at(self.line, self.source_line, text, reason)
+    }
+}
+
+fn xml_amount(tag: &XmlTag, attribute: &str) -> Result<u32, Diagnostic> {
+    tag.attributes
+        .get(attribute)
+        .and_then(|n| n.trim().parse().ok())
+        .ok_or_else(|| tag.diagnostic("missing or invalid quantity"))
+}
+
+fn xml_name(tag: &XmlTag, attribute: &str) -> Result<String, Diagnostic> {
+    tag.attributes
+        .get(attribute)
+        .filter(|n| !n.trim().is_empty())
+        .map(|n| n.trim().to_string())
+        .ok_or_else(|| tag.diagnostic("missing card name"))
+}
+
+fn parse_mtgo(text: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<DeckEntry> {
+    let mut entries = vec![];
+
+    for tag in xml_tags(text, diagnostics)
+        .iter()
+        .filter(|t| t.name == "Cards" && !t.closing)
+    {
+        let (amount, name) = match (xml_amount(tag, "Quantity"), xml_name(tag, "Name")) {
+            (Ok(amount), Ok(name)) => (amount, name),
+            (Err(e), _) | (_, Err(e)) => {
+                diagnostics.push(e);
+                continue;
+            }
+        };
+        let sideboard = tag
+            .attributes
+            .get("Sideboard")
+            .is_some_and(|s| s.eq_ignore_ascii_case("true"));
+
+        entries.push(DeckEntry {
+            line: tag.line,
+            amount,
+            name,
+            set: None,
+            collector_number: None,
+            zone: if sideboard {
+                Zone::Sideboard
+            } else {
+                Zone::Main
+            },
+        });
+    }
+
+    entries
+}
+
+fn parse_cockatrice(text: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<DeckEntry> {
+    let mut entries = vec![];
+    // cards in the "tokens" zone, or any other, aren't part of the deck
+    let mut zone = None;
+
+    for tag in xml_tags(text, diagnostics) {
+        match (tag.name, tag.closing) {
+            ("zone", false) => {
+                zone = match tag.attributes.get("name").map(String::as_str) {
+                    Some("main") => Some(Zone::Main),
+                    Some("side") => Some(Zone::Sideboard),
+                    _ => None,
+                }
+            }
+            ("zone", true) => zone = None,
+            ("card", false) => {
+                let Some(zone) = zone else { continue };
+                match (xml_amount(&tag, "number"), xml_name(&tag, "name")) {
+                    (Ok(amount), Ok(name)) => entries.push(DeckEntry {
+                        line: tag.line,
+                        amount,
+                        name,
+                        set: tag.attributes.get("setShortName").cloned(),
+                        collector_number: tag.attributes.get("collectorNumber").cloned(),
+                        zone,
+                    }),
+                    (Err(e), _) | (_, Err(e)) => diagnostics.push(e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
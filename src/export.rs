@@ -0,0 +1,440 @@
+use std::fmt::Write;
+
+use crate::{
+    card::{CardObject, SearchResult},
+    decklist::{DeckEntry, DeckFormat, Zone},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::{CardFace, ResponseList},
+        decklist::parse,
+    };
+    use std::fs::read_to_string;
+
+    fn entry(line: usize, amount: u32, name: &str, zone: Zone) -> DeckEntry {
+        DeckEntry {
+            line,
+            amount,
+            name: name.to_string(),
+            set: None,
+            collector_number: None,
+            zone,
+        }
+    }
+
+    fn resolved_deck() -> (Vec<DeckEntry>, Vec<(u32, SearchResult)>) {
+        let json_str = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let tasigur = response.data[0].clone();
+        let face = |name: &str| CardFace {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let kellan = CardObject {
+            name: "Kellan, Daring Traveler // Journey On".to_string(),
+            layout: "adventure".to_string(),
+            set: "woe".to_string(),
+            collector_number: "14".to_string(),
+            card_faces: vec![face("Kellan, Daring Traveler"), face("Journey On")],
+            ..Default::default()
+        };
+        let card = |name: &str| CardObject {
+            name: name.to_string(),
+            set: "m10".to_string(),
+            collector_number: "146".to_string(),
+            ..Default::default()
+        };
+
+        let entries = vec![
+            entry(1, 1, "Kellan, Daring Traveler", Zone::Commander),
+            entry(2, 1, "tasigur", Zone::Main),
+            entry(3, 2, "Tasigur, the Golden Fang", Zone::Main),
+            entry(4, 3, "Tasgur", Zone::Main),
+            entry(5, 1, "Tasigur, the Golden Fang", Zone::Sideboard),
+            entry(6, 2, "Lightning", Zone::Main),
+        ];
+        let results = vec![
+            (1, SearchResult::OneHit(kellan)),
+            (1, SearchResult::OneHit(tasigur.clone())),
+            (2, SearchResult::OneHit(tasigur.clone())),
+            (3, SearchResult::NoHits("Tasgur".to_string(), vec![])),
+            (1, SearchResult::OneHit(tasigur)),
+            (
+                2,
+                SearchResult::MultipleHits(
+                    "Lightning".to_string(),
+                    2,
+                    card("Lightning Bolt"),
+                    vec![card("Lightning Bolt"), card("Lightning Axe")],
+                ),
+            ),
+        ];
+        (entries, results)
+    }
+
+    #[test]
+    fn test_export_arena() {
+        let (entries, results) = resolved_deck();
+        let text = export(DeckFormat::Arena, &entries, &results).unwrap();
+
+        assert_eq!(
+            text,
+            "Commander\n1 Kellan, Daring Traveler (WOE) 14\n\nDeck\n3 Tasigur, the Golden Fang (UMA) 117\n// line 4: 3 Tasgur, no card with that name\n// line 6: 2 Lightning, 2 hits, using Lightning Bolt, the others include Lightning Axe\n2 Lightning Bolt (M10) 146\n\nSideboard\n1 Tasigur, the Golden Fang (UMA) 117\n"
+        );
+    }
+
+    #[test]
+    fn test_export_keeps_printings() {
+        let opt = |set: &str, number: &str| CardObject {
+            name: "Opt".to_string(),
+            set: set.to_string(),
+            collector_number: number.to_string(),
+            ..Default::default()
+        };
+        let entries = vec![
+            entry(1, 1, "Opt", Zone::Main),
+            entry(2, 3, "Opt", Zone::Main),
+            entry(3, 2, "Opt", Zone::Main),
+        ];
+        let results = vec![
+            (1, SearchResult::OneHit(opt("xln", "65"))),
+            (3, SearchResult::OneHit(opt("eld", "59"))),
+            (2, SearchResult::OneHit(opt("xln", "65"))),
+        ];
+
+        assert_eq!(
+            export(DeckFormat::Plain, &entries, &results).unwrap(),
+            "3 Opt (XLN) 65\n3 Opt (ELD) 59\n"
+        );
+    }
+
+    #[test]
+    fn test_export_plain() {
+        let (entries, results) = resolved_deck();
+        let text = export(DeckFormat::Plain, &entries, &results).unwrap();
+
+        assert_eq!(
+            text,
+            "3 Tasigur, the Golden Fang (UMA) 117\n// line 4: 3 Tasgur, no card with that name\n// line 6: 2 Lightning, 2 hits, using Lightning Bolt, the others include Lightning Axe\n2 Lightning Bolt (M10) 146\n\nCommander\n1 Kellan, Daring Traveler // Journey On (WOE) 14\n\nSideboard\n1 Tasigur, the Golden Fang (UMA) 117\n"
+        );
+        // without other zones there's no header, and the list is still plain text
+        let main = export(DeckFormat::Plain, &entries[1..4], &results[1..4]).unwrap();
+        assert_eq!(parse(&main).unwrap().format, DeckFormat::Plain);
+    }
+
+    #[test]
+    fn test_export_mtgo_ids() {
+        let (entries, results) = resolved_deck();
+        let text = export(DeckFormat::Mtgo, &entries, &results).unwrap();
+
+        assert!(text
+            .contains("<Cards Quantity=\"1\" Sideboard=\"true\" Name=\"Kellan, Daring Traveler\""));
+        assert!(text.contains("<Cards CatID=\"70019\" Quantity=\"3\""));
+    }
+
+    #[test]
+    fn test_xml_comment() {
+        assert_eq!(
+            xml_comment("2 Fire & Ice -- 3---"),
+            "<!-- 2 Fire & Ice - - 3- - - -->"
+        );
+    }
+
+    #[test]
+    fn test_export_reads_back() {
+        let (entries, results) = resolved_deck();
+        let kellan = (1, "Kellan, Daring Traveler", Zone::Commander);
+        let main = (3, "Tasigur, the Golden Fang", Zone::Main);
+        let sideboard = (1, "Tasigur, the Golden Fang", Zone::Sideboard);
+        let guessed = (2, "Lightning Bolt", Zone::Main);
+
+        for (format, read_as, expected) in [
+            // the headers above the other zones make plain text read as an arena list
+            (
+                DeckFormat::Plain,
+                DeckFormat::Arena,
+                [main, guessed, kellan, sideboard],
+            ),
+            (
+                DeckFormat::Arena,
+                DeckFormat::Arena,
+                [kellan, main, guessed, sideboard],
+            ),
+            // a .dek only has a main deck and a sideboard, the commander goes in the sideboard
+            (
+                DeckFormat::Mtgo,
+                DeckFormat::Mtgo,
+                [(1, kellan.1, Zone::Sideboard), main, guessed, sideboard],
+            ),
+            (
+                DeckFormat::Moxfield,
+                DeckFormat::Moxfield,
+                [kellan, main, guessed, sideboard],
+            ),
+        ] {
+            let list = parse(&export(format, &entries, &results).unwrap()).unwrap();
+            let read: Vec<(u32, &str, Zone)> = list
+                .entries
+                .iter()
+                // plain text and moxfield write both halves of an adventure card
+                .map(|e| (e.amount, e.name.split(" // ").next().unwrap(), e.zone))
+                .collect();
+
+            assert_eq!(list.format, read_as, "{}", format);
+            assert!(list.diagnostics.is_empty(), "{}", format);
+            assert_eq!(read, expected, "{}", format);
+        }
+        assert!(export(DeckFormat::Cockatrice, &entries, &results).is_none());
+    }
+}
+
+/// the order the zones are written in
+const ZONES: [Zone; 4] = [
+    Zone::Commander,
+    Zone::Companion,
+    Zone::Main,
+    Zone::Sideboard,
+];
+
+/// a line of the exported deck
+enum Line<'a> {
+    /// every line of a zone that resolved to this printing, their amounts added up
+    Card(u32, &'a CardObject),
+    /// a decklist line that didn't resolve or was a guess, and why, written as a comment so it isn't lost
+    Comment(&'a DeckEntry, String),
+}
+
+/// writes the resolved deck in `format`, with the name, set and collector number scryfall has for every card.
+/// `search_results` lines up with `entries`, lines that didn't resolve are kept as comments.
+/// `None` for cockatrice decks, which are only read
+pub fn export(
+    format: DeckFormat,
+    entries: &[DeckEntry],
+    search_results: &[(u32, SearchResult)],
+) -> Option<String> {
+    let mut zones: Vec<(Zone, Vec<Line>)> = ZONES
+        .iter()
+        .map(|zone| (*zone, zone_lines(*zone, entries, search_results)))
+        .filter(|(_, lines)| !lines.is_empty())
+        .collect();
+
+    let text = match format {
+        DeckFormat::Plain => {
+            // the main deck goes first and without a header, a deck that is only a main deck stays plain
+            zones.sort_by_key(|(zone, _)| *zone != Zone::Main);
+            write_text(
+                &zones,
+                |zone| match zone {
+                    Zone::Main => None,
+                    Zone::Commander => Some("Commander"),
+                    Zone::Companion => Some("Companion"),
+                    Zone::Sideboard => Some("Sideboard"),
+                },
+                |amount, card| format!("{} {}", amount, printing(&card.name, card, true)),
+            )
+        }
+        DeckFormat::Arena => write_text(
+            &zones,
+            |zone| {
+                Some(match zone {
+                    Zone::Commander => "Commander",
+                    Zone::Companion => "Companion",
+                    Zone::Main => "Deck",
+                    Zone::Sideboard => "Sideboard",
+                })
+            },
+            |amount, card| format!("{} {}", amount, printing(front_name(card), card, true)),
+        ),
+        DeckFormat::Moxfield => write_text(
+            &zones,
+            |zone| {
+                Some(match zone {
+                    Zone::Commander => "COMMANDER:",
+                    Zone::Companion => "COMPANION:",
+                    Zone::Main => "MAINBOARD:",
+                    Zone::Sideboard => "SIDEBOARD:",
+                })
+            },
+            |amount, card| format!("{}x {}", amount, printing(&card.name, card, false)),
+        ),
+        DeckFormat::Mtgo => write_mtgo(&zones),
+        DeckFormat::Cockatrice => return None,
+    };
+
+    Some(text)
+}
+
+fn zone_lines<'a>(
+    zone: Zone,
+    entries: &'a [DeckEntry],
+    search_results: &'a [(u32, SearchResult)],
+) -> Vec<Line<'a>> {
+    let mut lines: Vec<Line> = vec![];
+
+    for (entry, (amount, search_result)) in entries.iter().zip(search_results) {
+        if entry.zone != zone {
+            continue;
+        }
+        let card = match search_result {
+            SearchResult::OneHit(card) => card,
+            SearchResult::MultipleHits(_, total, card, candidates) => {
+                let others: Vec<&str> = candidates
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .filter(|name| *name != card.name)
+                    .collect();
+                let reason = format!(
+                    "{} hits, using {}, the others include {}",
+                    total,
+                    card.name,
+                    others.join(", ")
+                );
+                lines.push(Line::Comment(entry, reason));
+                card
+            }
+            SearchResult::NoHits(_, _) => {
+                lines.push(Line::Comment(entry, "no card with that name".to_string()));
+                continue;
+            }
+            SearchResult::Failed(_, _) => {
+                lines.push(Line::Comment(entry, "could not be looked up".to_string()));
+                continue;
+            }
+        };
+
+        // other printings of the card keep their own line, the export is the deck as it was listed
+        let same_printing = lines.iter_mut().find_map(|line| match line {
+            Line::Card(total, c)
+                if c.oracle_key() == card.oracle_key()
+                    && c.set.eq_ignore_ascii_case(&card.set)
+                    && c.collector_number == card.collector_number =>
+            {
+                Some(total)
+            }
+            _ => None,
+        });
+        match same_printing {
+            Some(total) => *total += amount,
+            None => lines.push(Line::Card(*amount, card)),
+        }
+    }
+
+    lines
+}
+
+/// "Name (SET) number", the set in upper or lower case
+fn printing(name: &str, card: &CardObject, upper_set: bool) -> String {
+    if card.set.is_empty() {
+        return name.to_string();
+    }
+    let set = if upper_set {
+        card.set.to_uppercase()
+    } else {
+        card.set.to_lowercase()
+    };
+    format!("{} ({}) {}", name, set, card.collector_number)
+}
+
+/// arena and mtgo only know the front of cards that turn over or have an adventure,
+/// split cards go by both halves
+fn front_name(card: &CardObject) -> &str {
+    match card.card_faces.first() {
+        Some(face) if !matches!(card.layout.as_str(), "split" | "aftermath" | "") => &face.name,
+        _ => &card.name,
+    }
+}
+
+/// the decklist line as it was read, for the comment an unresolved or guessed line turns into
+fn as_listed(entry: &DeckEntry, reason: &str) -> String {
+    let mut text = format!("line {}: {} {}", entry.line, entry.amount, entry.name);
+    if let Some(set) = &entry.set {
+        let _ = write!(text, " ({})", set);
+    }
+    if let Some(number) = &entry.collector_number {
+        let _ = write!(text, " {}", number);
+    }
+    let _ = write!(text, ", {}", reason);
+    text
+}
+
+/// the text formats: an optional header above every zone, a blank line between zones
+/// and "//" comments, which the decklist parser skips
+fn write_text(
+    zones: &[(Zone, Vec<Line>)],
+    header: impl Fn(Zone) -> Option<&'static str>,
+    card_line: impl Fn(u32, &CardObject) -> String,
+) -> String {
+    let mut out = String::new();
+
+    for (i, (zone, lines)) in zones.iter().enumerate() {
+        if i > 0 && header(*zone).is_some() {
+            out.push('\n');
+        }
+        if let Some(header) = header(*zone) {
+            let _ = writeln!(out, "{}", header);
+        }
+        for line in lines {
+            let _ = match line {
+                Line::Card(amount, card) => writeln!(out, "{}", card_line(*amount, card)),
+                Line::Comment(entry, reason) => {
+                    writeln!(out, "// {}", as_listed(entry, reason))
+                }
+            };
+        }
+    }
+
+    out
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// an xml comment, "--" isn't allowed in one. entities aren't read in comments, so nothing else is escaped
+fn xml_comment(text: &str) -> String {
+    let mut text = text.to_string();
+    // "---" still has a "--" after one pass
+    while text.contains("--") {
+        text = text.replace("--", "- -");
+    }
+    format!("<!-- {} -->", text)
+}
+
+fn write_mtgo(zones: &[(Zone, Vec<Line>)]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Deck xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n  <NetDeckID>0</NetDeckID>\n  <PreconstructedDeckID>0</PreconstructedDeckID>\n",
+    );
+
+    for (zone, lines) in zones {
+        for line in lines {
+            let _ = match line {
+                Line::Card(amount, card) => writeln!(
+                    out,
+                    "  <Cards {}Quantity=\"{}\" Sideboard=\"{}\" Name=\"{}\" Annotation=\"0\" />",
+                    // cards that were never on mtgo have no id to give
+                    card.mtgo_id
+                        .map(|id| format!("CatID=\"{}\" ", id))
+                        .unwrap_or_default(),
+                    amount,
+                    // mtgo keeps the companion and the commander in the sideboard
+                    matches!(zone, Zone::Companion | Zone::Commander | Zone::Sideboard),
+                    // mtgo writes split cards with a single slash
+                    escape_xml(&front_name(card).replace(" // ", "/"))
+                ),
+                Line::Comment(entry, reason) => {
+                    writeln!(out, "  {}", xml_comment(&as_listed(entry, reason)))
+                }
+            };
+        }
+    }
+
+    out.push_str("</Deck>\n");
+    out
+}
@@ -1,27 +1,138 @@
-use bulk_files::get_bulk_from_scryfall;
-use card::{CardObject, SearchResult};
-use search::get_from_scryfall;
+// the tests of a module come first, above the code they test
+#![allow(clippy::items_after_test_module)]
+// the slint! macro expands its component impls inside functions
+#![allow(non_local_definitions)]
 
-use slint::SharedString;
-use std::{fs::read_to_string, io};
+use bulk_files::{bulk_path, update_bulk, BulkKind, BULK_FOLDER};
+use cache::{CardCache, CACHE_FILE, DEFAULT_TTL};
+use card::{CardObject, SearchResult};
+use client::{Mode, ScryfallClient, SCRYFALL_API};
+use decklist::{DeckEntry, DeckFormat, Decklist, DecklistError, Zone};
+use offline::CardIndex;
+use search::{get_cached_or_from_scryfall, get_from_scryfall, CardQuery};
+
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel, Weak};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    env,
+    fs::{self, read_to_string},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 mod bulk_files;
+mod cache;
 mod card;
+mod client;
+mod compact;
+mod decklist;
+mod export;
+mod names;
+mod offline;
+mod query;
 mod search;
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn card(oracle_id: u128, name: &str, type_line: &str) -> CardObject {
+        CardObject {
+            oracle_id: Some(Uuid::from_u128(oracle_id)),
+            name: name.to_string(),
+            type_line: type_line.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(line: usize, amount: u32, name: &str) -> DeckEntry {
+        DeckEntry {
+            line,
+            amount,
+            name: name.to_string(),
+            set: None,
+            collector_number: None,
+            zone: Zone::Main,
+        }
+    }
+
+    #[test]
+    fn test_found_cards_adds_up_printings() {
+        let mut eld = card(1, "Opt", "Instant");
+        eld.set = "eld".to_string();
+        let results = vec![
+            (1, SearchResult::OneHit(card(1, "Opt", "Instant"))),
+            (3, SearchResult::OneHit(eld)),
+            (1, SearchResult::OneHit(card(2, "Duress", "Sorcery"))),
+        ];
+
+        let found: Vec<(u32, String)> = found_cards(&results)
+            .into_iter()
+            .map(|(amount, c)| (amount, c.name))
+            .collect();
+
+        assert_eq!(found, [(4, "Opt".to_string()), (1, "Duress".to_string())]);
+    }
+
+    #[test]
+    fn test_singleton_warnings() {
+        let entries = vec![
+            entry(1, 1, "Sol Ring"),
+            entry(2, 1, "Forest"),
+            entry(3, 1, "Sol Ring"),
+            entry(4, 5, "Forest"),
+        ];
+        let results = vec![
+            (1, SearchResult::OneHit(card(1, "Sol Ring", "Artifact"))),
+            (
+                1,
+                SearchResult::OneHit(card(2, "Forest", "Basic Land — Forest")),
+            ),
+            (1, SearchResult::OneHit(card(1, "Sol Ring", "Artifact"))),
+            (
+                5,
+                SearchResult::OneHit(card(2, "Forest", "Basic Land — Forest")),
+            ),
+        ];
+
+        assert_eq!(
+            singleton_warnings(&entries, &results),
+            ["line 1, 3: 2 copies of Sol Ring, a singleton deck can only have one"]
+        );
+        assert!(!warning_display(&[], &entries, &results, false).contains("singleton"));
+        assert!(warning_display(&[], &entries, &results, true).contains("singleton"));
+    }
+}
+
 slint::slint! {
     import { CheckBox , Button, GroupBox, LineEdit} from "std-widgets.slint";
 
+    export struct Miss {
+        index: int,
+        line: int,
+        suggestions: [string],
+    }
+
     export component AppWindow inherits Window {
-        out property <bool> commander;
-        out property <bool> companion;
+        in-out property <bool> commander;
+        in-out property <bool> companion;
         out property <string> cards;
         out property <string> ramp;
         out property <string> draw;
         in property <string> answer;
         in property <string> info;
         in property <string> errors;
+        in property <bool> can_retry;
+        in property <[Miss]> misses;
         callback do_the_thing();
+        callback retry_failed();
+        callback accept_suggestion(int, string);
+        callback commander_toggled();
         VerticalLayout {
             spacing: 5px;
             padding: 5px;
@@ -33,6 +144,28 @@ slint::slint! {
                 text: errors;
             }
 
+            for miss in root.misses : HorizontalLayout {
+                spacing: 5px;
+                Text {
+                    font-size: 12px;
+                    vertical-alignment: center;
+                    text: "line " + miss.line + ", did you mean";
+                }
+                for suggestion in miss.suggestions : Button {
+                    text: suggestion;
+                    clicked => {
+                        root.accept_suggestion(miss.index, suggestion);
+                    }
+                }
+            }
+
+            if root.can_retry : Button {
+                text: "retry failed cards";
+                clicked => {
+                    root.retry_failed();
+                }
+            }
+
             Text {
                 font-size: 14px;
                 horizontal-alignment: center;
@@ -47,16 +180,15 @@ slint::slint! {
                     CheckBox {
                         height: 25px;
                         text: "commander";
+                        checked <=> root.commander;
                         toggled => {
-                            root.commander = !root.commander;
+                            root.commander_toggled();
                         }
                     }
                     CheckBox {
                         height: 25px;
                         text: "companion";
-                        toggled => {
-                            root.companion = !root.companion;
-                        }
+                        checked <=> root.companion;
                     }
                 }
             }
@@ -109,51 +241,463 @@ slint::slint! {
 
 const FILE: &str = "list.txt";
 
+/// `land-calc [decklist] [--api-url URL] [--record FOLDER | --replay FOLDER] [--cache-days DAYS] [--update-bulk] [--offline] [--bulk-type TYPE] [--query QUERY] [--export PATH] [--export-format FORMAT]`
+struct Args {
+    decklist: String,
+    api_url: String,
+    mode: Mode,
+    /// how long a cached card is trusted, zero turns the cache off
+    cache_ttl: Duration,
+    /// fetch scryfall's bulk card file before starting, if it changed
+    update_bulk: bool,
+    /// look cards up in the bulk file instead of asking scryfall
+    offline: bool,
+    /// which of scryfall's bulk files `update_bulk` and `offline` use
+    bulk_kind: BulkKind,
+    /// print the cards in the bulk file this search matches, and quit
+    query: Option<String>,
+    /// write the resolved decklist here, and quit
+    export: Option<PathBuf>,
+    /// the format `export` is written in, by default picked from its extension
+    export_format: Option<DeckFormat>,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            decklist: FILE.to_string(),
+            api_url: SCRYFALL_API.to_string(),
+            mode: Mode::Live,
+            cache_ttl: DEFAULT_TTL,
+            update_bulk: false,
+            offline: false,
+            bulk_kind: BulkKind::default(),
+            query: None,
+            export: None,
+            export_format: None,
+        }
+    }
+}
+
+impl Args {
+    fn read(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Args::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+            match arg.as_str() {
+                "--api-url" => parsed.api_url = value()?,
+                "--record" => parsed.mode = Mode::Record(value()?.into()),
+                "--replay" => parsed.mode = Mode::Replay(value()?.into()),
+                "--cache-days" => {
+                    let seconds = value()?
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|days| days.checked_mul(24 * 60 * 60))
+                        .ok_or(format!("{} needs a whole number of days", arg))?;
+                    parsed.cache_ttl = Duration::from_secs(seconds);
+                }
+                "--update-bulk" => parsed.update_bulk = true,
+                "--offline" => parsed.offline = true,
+                "--bulk-type" => parsed.bulk_kind = value()?.parse()?,
+                "--query" => parsed.query = Some(value()?),
+                "--export" => parsed.export = Some(value()?.into()),
+                "--export-format" => parsed.export_format = Some(value()?.parse()?),
+                option if option.starts_with("--") => {
+                    return Err(format!("unknown option {}", option))
+                }
+                _ => parsed.decklist = arg.clone(),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), reqwest::Error> {
-    let list = read_decklist().expect("no file called 'list.txt' found");
+    let mut warnings = vec!["warnings:".to_string()];
 
-    // let _temp = get_bulk_from_scryfall().await?;
-    // return Ok(());
+    // carrying on without the other options could quietly go live instead of replaying
+    let args = Args::read(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
 
-    let mut warnings = vec!["warnings:".to_string()];
+    // recording and replaying have to see every request, a cache would hide them
+    let cache = (args.mode == Mode::Live && !args.cache_ttl.is_zero()).then(|| {
+        Arc::new(Mutex::new(CardCache::load(
+            CACHE_FILE.into(),
+            args.cache_ttl,
+        )))
+    });
+    let client = Arc::new(ScryfallClient::new(&args.api_url, args.mode)?);
 
-    let search_results: Vec<(u32, SearchResult)> = get_from_scryfall(list).await?;
+    if args.update_bulk {
+        if let Err(e) = refresh_bulk(&client, args.bulk_kind).await {
+            warnings.push(format!("could not update the bulk data: {}", e));
+        }
+    }
+
+    if let Some(query) = &args.query {
+        if let Err(e) = print_search(query, args.bulk_kind) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let deck = read_decklist(&args.decklist).unwrap_or_else(|e| {
+        warnings.push(e.to_string());
+        Decklist {
+            format: DeckFormat::Plain,
+            entries: vec![],
+            diagnostics: vec![],
+        }
+    });
+    warnings.extend(deck.diagnostics.iter().map(|d| d.to_string()));
+
+    let list: Vec<(u32, CardQuery)> = deck
+        .playables()
+        .map(|entry| (entry.amount, entry.into()))
+        .collect();
+
+    let online = Source::Scryfall { client, cache };
+    let source = if args.offline {
+        let path = bulk_path(Path::new(BULK_FOLDER), args.bulk_kind);
+        match tokio::task::spawn_blocking(move || CardIndex::load(&path)).await {
+            Ok(Ok(index)) => {
+                if index.skipped > 0 {
+                    warnings.push(format!(
+                        "{} cards of the bulk data could not be read and were left out",
+                        index.skipped
+                    ));
+                }
+                Source::Offline(Arc::new(index))
+            }
+            Ok(Err(e)) => {
+                warnings.push(format!(
+                    "could not read the bulk data, asking scryfall instead: {}",
+                    e
+                ));
+                online
+            }
+            Err(e) => {
+                warnings.push(format!(
+                    "reading the bulk data stopped, asking scryfall instead: {}",
+                    e
+                ));
+                online
+            }
+        }
+    } else {
+        online
+    };
+
+    if let Some(path) = &args.export {
+        let exported = match export_format(path, args.export_format) {
+            Ok(format) => export_deck(&source, &deck, path, format, &warnings).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = exported {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let (search_results, cache_error) = look_up(&source, list).await;
+    warnings.extend(cache_error);
+
+    let session = Session {
+        source,
+        format: deck.format,
+        warnings: RefCell::new(warnings),
+        entries: deck.playables().cloned().collect(),
+        search_results: RefCell::new(search_results),
+    };
+
+    run_app(session, &deck, &args.decklist).expect("slint did not initialize");
+    Ok(())
+}
+
+/// downloads the bulk file if scryfall has a newer one, showing progress on the terminal,
+/// and builds the offline index from it so a broken file is noticed now, not on the first `--offline` start
+async fn refresh_bulk(
+    client: &ScryfallClient,
+    kind: BulkKind,
+) -> Result<(), bulk_files::BulkError> {
+    let mut shown = None;
+    let bulk = update_bulk(client, Path::new(BULK_FOLDER), kind, |done, total| {
+        let percent = done * 100 / total.max(1);
+        if shown != Some(percent) {
+            shown = Some(percent);
+            print!("\rdownloading {}: {}%", kind, percent);
+            let _ = io::stdout().flush();
+        }
+    })
+    .await?;
+    if shown.is_some() {
+        println!();
+    }
+
+    // an unchanged bulk file reads its saved index instead of the whole file.
+    // a panic while reading is passed on as if the index had been read right here
+    let path = bulk.path.clone();
+    let index = tokio::task::spawn_blocking(move || CardIndex::load(&path))
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))?;
+    println!("{} from {}: {} cards", kind, bulk.updated_at, index.len());
+    if index.skipped > 0 {
+        println!(
+            "{} cards could not be read and were left out",
+            index.skipped
+        );
+    }
+    Ok(())
+}
+
+/// `--query`, for scripts: prints the name of every card in the bulk file the search matches
+fn print_search(text: &str, kind: BulkKind) -> Result<(), String> {
+    let query = query::parse(text).map_err(|e| e.to_string())?;
+    let index =
+        CardIndex::load(&bulk_path(Path::new(BULK_FOLDER), kind)).map_err(|e| e.to_string())?;
+
+    // every printing of a card matches, the name is printed once
+    let mut printed = HashSet::new();
+    for card in index.search(&query) {
+        if printed.insert(card.name.as_str()) {
+            println!("{}", card.name);
+        }
+    }
+
+    Ok(())
+}
 
-    let cards: Vec<(u32, CardObject)> = search_results
+/// `--export`, for scripts: looks up every line of the deck, sideboard included, and writes it to `path`.
+/// the warnings and the lines that couldn't be resolved are printed
+async fn export_deck(
+    source: &Source,
+    deck: &Decklist,
+    path: &Path,
+    format: DeckFormat,
+    warnings: &[String],
+) -> Result<(), String> {
+    let list = deck
+        .entries
         .iter()
-        .filter_map(|(amount, search_result)| match search_result {
-            SearchResult::MultipleHits(_, _, c) => Some((*amount, c.clone())),
-            SearchResult::OneHit(c) => Some((*amount, c.clone())),
-            SearchResult::NoHits(_) => None,
-        })
+        .map(|entry| (entry.amount, entry.into()))
         .collect();
+    let (search_results, cache_error) = look_up(source, list).await;
+
+    let text = export::export(format, &deck.entries, &search_results)
+        .ok_or_else(|| format!("{} decks can't be exported", format))?;
+    fs::write(path, text).map_err(|e| format!("could not write {}: {}", path.display(), e))?;
 
-    let mut errors: Vec<String> = search_results
+    // the first warning is the "warnings:" heading the window shows
+    for warning in warnings.iter().skip(1).chain(&cache_error) {
+        eprintln!("{}", warning);
+    }
+    let unresolved = search_results
         .iter()
-        .filter_map(|(_, search_result)| match search_result {
-            SearchResult::MultipleHits(q, i, card) => Some(format!(
-                "{} hits for {}, using the first one: {}",
-                i, q, card.name
-            )),
-            SearchResult::OneHit(_) => None,
-            SearchResult::NoHits(q) => Some(format!("No card with name {} found", q)),
+        .filter(|(_, r)| matches!(r, SearchResult::NoHits(_, _) | SearchResult::Failed(_, _)))
+        .count();
+    println!(
+        "wrote a {} deck to {}, {} line(s) could not be resolved and are comments",
+        format,
+        path.display(),
+        unresolved
+    );
+    Ok(())
+}
+
+/// the format asked for, or else the one the extension says: .dek files are mtgo decks,
+/// anything else is plain text. cockatrice decks are only ever read
+fn export_format(path: &Path, chosen: Option<DeckFormat>) -> Result<DeckFormat, String> {
+    let format = chosen.unwrap_or_else(|| match path.extension().and_then(|e| e.to_str()) {
+        Some("dek") => DeckFormat::Mtgo,
+        Some("cod") => DeckFormat::Cockatrice,
+        _ => DeckFormat::Plain,
+    });
+
+    match format {
+        DeckFormat::Cockatrice => Err(format!(
+            "{} decks can't be exported, try plain, arena, mtgo or moxfield",
+            format
+        )),
+        _ => Ok(format),
+    }
+}
+
+/// where cards are looked up
+#[derive(Clone)]
+enum Source {
+    Scryfall {
+        client: Arc<ScryfallClient>,
+        cache: Option<Arc<Mutex<CardCache>>>,
+    },
+    /// the bulk file, without the network
+    Offline(Arc<CardIndex>),
+}
+
+/// resolves the list from `source`, through the cache if there is one, and saves what was learned.
+/// a cache that can't be saved is only worth a warning
+async fn look_up(
+    source: &Source,
+    list: Vec<(u32, CardQuery)>,
+) -> (Vec<(u32, SearchResult)>, Option<String>) {
+    let (client, cache) = match source {
+        Source::Offline(index) => return (index.resolve(list), None),
+        Source::Scryfall { client, cache } => (client, cache),
+    };
+    let Some(cache) = cache else {
+        return (get_from_scryfall(client, list).await, None);
+    };
+
+    let search_results = get_cached_or_from_scryfall(client, cache, list).await;
+    let saved = cache.lock().expect("cache lock poisoned").save();
+
+    (
+        search_results,
+        saved
+            .err()
+            .map(|e| format!("could not save {}: {}", CACHE_FILE, e)),
+    )
+}
+
+/// the cards that were found, each card once: printings on different lines are added up by oracle_id
+fn found_cards(search_results: &[(u32, SearchResult)]) -> Vec<(u32, CardObject)> {
+    let mut cards: Vec<(u32, CardObject)> = vec![];
+    let mut positions: HashMap<_, usize> = HashMap::new();
+
+    let found = search_results
+        .iter()
+        .filter_map(|(amount, search_result)| match search_result {
+            SearchResult::MultipleHits(_, _, c, _) => Some((*amount, c)),
+            SearchResult::OneHit(c) => Some((*amount, c)),
+            SearchResult::NoHits(_, _) => None,
+            SearchResult::Failed(_, _) => None,
+        });
+    for (amount, card) in found {
+        match positions.get(&card.oracle_key()) {
+            Some(&i) => cards[i].0 += amount,
+            None => {
+                positions.insert(card.oracle_key(), cards.len());
+                cards.push((amount, card.clone()));
+            }
+        }
+    }
+
+    cards
+}
+
+/// cards a commander deck holds more copies of than it may, counted over every line and printing
+fn singleton_warnings(
+    entries: &[DeckEntry],
+    search_results: &[(u32, SearchResult)],
+) -> Vec<String> {
+    let mut copies: Vec<(&CardObject, u32, Vec<String>)> = vec![];
+    for (entry, (amount, search_result)) in entries.iter().zip(search_results) {
+        let (SearchResult::OneHit(card) | SearchResult::MultipleHits(_, _, card, _)) =
+            search_result
+        else {
+            continue;
+        };
+        if card.copies_allowed().is_none() {
+            continue;
+        }
+        match copies
+            .iter_mut()
+            .find(|(c, _, _)| c.oracle_key() == card.oracle_key())
+        {
+            Some((_, total, lines)) => {
+                *total += amount;
+                lines.push(entry.line.to_string());
+            }
+            None => copies.push((card, *amount, vec![entry.line.to_string()])),
+        }
+    }
+
+    copies
+        .into_iter()
+        .filter_map(|(card, total, lines)| {
+            let allowed = card.copies_allowed()?;
+            (total > allowed).then(|| {
+                let allowed = match allowed {
+                    1 => "one".to_string(),
+                    n => n.to_string(),
+                };
+                format!(
+                    "line {}: {} copies of {}, a singleton deck can only have {}",
+                    lines.join(", "),
+                    total,
+                    card.name,
+                    allowed
+                )
+            })
         })
-        .collect();
+        .collect()
+}
 
-    warnings.append(&mut errors);
-    let warning_display: String = warnings.join("\n");
+/// `entries` holds the decklist line each search result came from,
+/// `commander` is the box in the window, ticked for a singleton deck
+fn warning_display(
+    warnings: &[String],
+    entries: &[DeckEntry],
+    search_results: &[(u32, SearchResult)],
+    commander: bool,
+) -> String {
+    let singleton = if commander {
+        singleton_warnings(entries, search_results)
+    } else {
+        vec![]
+    };
 
-    let number_of_spells: usize = cards.iter().filter(|(_, c)| c.is_nonland()).count();
-    let lands_func = recommended_lands_static_cards(cards);
+    let errors = entries
+        .iter()
+        .zip(search_results)
+        .filter_map(|(entry, (_, search_result))| {
+            let error = match search_result {
+                SearchResult::MultipleHits(q, i, card, _) => {
+                    Some(format!("{} hits for {}, using {}", i, q, card.name))
+                }
+                SearchResult::OneHit(card) if card.spelled_as(&entry.name) => None,
+                SearchResult::OneHit(card) if card.has_oracle_name(&entry.name) => Some(format!(
+                    "read {} as {}, they only differ in accents, punctuation or spacing",
+                    entry.name, card.name
+                )),
+                // a flavor name, a name in another language or a suggestion that was picked
+                SearchResult::OneHit(card) => {
+                    Some(format!("listed as {}, which is {}", entry.name, card.name))
+                }
+                SearchResult::NoHits(q, suggestions) if suggestions.is_empty() => {
+                    Some(format!("No card with name {} found", q))
+                }
+                SearchResult::NoHits(q, suggestions) => Some(format!(
+                    "No card with name {} found, did you mean {}?",
+                    q,
+                    suggestions.join(", ")
+                )),
+                SearchResult::Failed(q, e) => {
+                    Some(format!("Could not look up {}: {}", q.name(), e))
+                }
+            };
+            error.map(|e| format!("line {}: {}", entry.line, e))
+        });
 
-    run_app(lands_func, number_of_spells, warning_display).expect("slint did not initialize");
-    Ok(())
+    warnings
+        .iter()
+        .cloned()
+        .chain(errors)
+        .chain(singleton)
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 fn recommended_lands(
     total_cards: u32,
-    list: &Vec<(u32, CardObject)>,
+    list: &[(u32, CardObject)],
     ramp: u32,
     draw: u32,
     cmdr_cmp: u32,
@@ -161,50 +705,177 @@ fn recommended_lands(
     let total_mana_value: u32 = list
         .iter()
         .map(|(a, c)| *a * c.cmc.as_f64().unwrap() as u32)
-        .fold(0, |a, b| a + b);
+        .sum();
     let average_mv = total_mana_value as f64 / total_cards as f64;
 
     (total_cards as f64 / 60.0) * (19.59 + 1.9 * average_mv + 0.27 * cmdr_cmp as f64)
         - 0.28 * (ramp + draw) as f64
 }
 
-fn recommended_lands_static_cards<'a>(
-    list: Vec<(u32, CardObject)>,
-) -> impl Fn(u32, u32, u32, u32) -> f64 + 'a {
-    move |total_cards: u32, ramp: u32, draw: u32, cmdr_cmp: u32| {
-        recommended_lands(total_cards, &list, ramp, draw, cmdr_cmp)
-    }
-}
-
 fn parse_shared_string_u32(s: SharedString) -> u32 {
     if s.is_empty() {
-        return 0;
+        0
     } else {
-        return s.parse().unwrap();
+        s.parse().unwrap()
     }
 }
 
-fn run_app<F>(
-    land_finder_function: F,
-    spells: usize,
-    errors: String,
-) -> Result<(), slint::PlatformError>
-where
-    F: Fn(u32, u32, u32, u32) -> f64 + 'static,
-{
-    let ui = AppWindow::new()?;
+/// what the window shows, kept so failed and misspelled cards can be looked up again
+struct Session {
+    source: Source,
+    format: DeckFormat,
+    warnings: RefCell<Vec<String>>,
+    /// the decklist line each search result came from
+    entries: Vec<DeckEntry>,
+    search_results: RefCell<Vec<(u32, SearchResult)>>,
+}
 
-    let ui_handle = ui.as_weak().unwrap();
+fn show_results(ui: &AppWindow, session: &Session) {
+    let search_results = session.search_results.borrow();
 
-    ui_handle.set_info(
+    let spells: usize = found_cards(&search_results)
+        .iter()
+        .filter(|(_, c)| c.is_nonland())
+        .count();
+    let can_retry = search_results
+        .iter()
+        .any(|(_, r)| matches!(r, SearchResult::Failed(_, _)));
+    let misses: Vec<Miss> = session
+        .entries
+        .iter()
+        .zip(search_results.iter())
+        .enumerate()
+        .filter_map(|(i, (entry, (_, search_result)))| {
+            let suggestions: Vec<SharedString> = match search_result {
+                SearchResult::NoHits(_, suggestions) => {
+                    suggestions.iter().map(SharedString::from).collect()
+                }
+                SearchResult::MultipleHits(_, _, _, candidates) => candidates
+                    .iter()
+                    .map(|c| SharedString::from(&c.name))
+                    .collect(),
+                _ => vec![],
+            };
+
+            (!suggestions.is_empty()).then(|| Miss {
+                index: i as i32,
+                line: entry.line as i32,
+                suggestions: ModelRc::new(VecModel::from(suggestions)),
+            })
+        })
+        .collect();
+
+    ui.set_info(
         format!(
-            "Detected a deck with {} spells.\nFill in the following info\nto get a recommandation.",
-            spells
+            "Detected a {} deck with {} spells.\nFill in the following info\nto get a recommandation.",
+            session.format, spells
         )
         .into(),
     );
-    ui_handle.set_errors(errors.into());
-    ui_handle.set_answer("tries to read 'list.txt'".into());
+    ui.set_errors(
+        warning_display(
+            &session.warnings.borrow(),
+            &session.entries,
+            &search_results,
+            ui.get_commander(),
+        )
+        .into(),
+    );
+    ui.set_can_retry(can_retry);
+    ui.set_misses(ModelRc::new(VecModel::from(misses)));
+}
+
+/// looks the given search results up again, and shows the new answers when they arrive
+fn resolve_again(session: Rc<Session>, ui: Weak<AppWindow>, redo: Vec<(usize, (u32, CardQuery))>) {
+    let (indices, list): (Vec<usize>, Vec<(u32, CardQuery)>) = redo.into_iter().unzip();
+
+    let source = session.source.clone();
+    let resolving = tokio::spawn(async move { look_up(&source, list).await });
+
+    if let Some(ui) = ui.upgrade() {
+        ui.set_can_retry(false);
+    }
+
+    slint::spawn_local(async move {
+        let Ok((resolved, cache_error)) = resolving.await else {
+            return;
+        };
+        session.warnings.borrow_mut().extend(cache_error);
+        for (i, result) in indices.into_iter().zip(resolved) {
+            session.search_results.borrow_mut()[i] = result;
+        }
+        if let Some(ui) = ui.upgrade() {
+            show_results(&ui, &session);
+        }
+    })
+    .expect("slint event loop is running");
+}
+
+fn run_app(session: Session, deck: &Decklist, decklist: &str) -> Result<(), slint::PlatformError> {
+    let ui = AppWindow::new()?;
+
+    let ui_handle = ui.as_weak().unwrap();
+    let session = Rc::new(session);
+
+    ui_handle.set_commander(deck.has_zone(Zone::Commander));
+    ui_handle.set_companion(deck.has_zone(Zone::Companion));
+    show_results(&ui_handle, &session);
+    ui_handle.set_answer(format!("tries to read '{}'", decklist).into());
+
+    let retry_session = session.clone();
+    let retry_handle = ui.as_weak();
+    ui.on_retry_failed(move || {
+        let failed: Vec<(usize, (u32, CardQuery))> = retry_session
+            .search_results
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (amount, search_result))| match search_result {
+                SearchResult::Failed(q, _) => Some((i, (*amount, q.clone()))),
+                _ => None,
+            })
+            .collect();
+
+        resolve_again(retry_session.clone(), retry_handle.clone(), failed);
+    });
+
+    // a commander deck is a singleton deck, its warnings come and go with the box
+    let toggled_session = session.clone();
+    let toggled_handle = ui.as_weak();
+    ui.on_commander_toggled(move || {
+        if let Some(ui) = toggled_handle.upgrade() {
+            show_results(&ui, &toggled_session);
+        }
+    });
+
+    let accept_session = session.clone();
+    let accept_handle = ui.as_weak();
+    ui.on_accept_suggestion(move |index, name| {
+        let index = index as usize;
+        let (amount, query) = match &accept_session.search_results.borrow()[index] {
+            // a candidate's name could match several cards again, its oracle_id can't
+            (amount, SearchResult::MultipleHits(_, _, _, candidates)) => (
+                *amount,
+                candidates
+                    .iter()
+                    .find(|c| c.name == name.as_str())
+                    .map(|c| CardQuery::OracleId {
+                        name: c.name.clone(),
+                        oracle_id: c.oracle_key(),
+                    }),
+            ),
+            (amount, _) => (*amount, None),
+        };
+        // the set and number of the line still say which printing was meant
+        let query =
+            query.unwrap_or_else(|| CardQuery::from(&accept_session.entries[index]).renamed(&name));
+
+        resolve_again(
+            accept_session.clone(),
+            accept_handle.clone(),
+            vec![(index, (amount, query))],
+        );
+    });
 
     ui.on_do_the_thing(move || {
         let commander = ui_handle.get_commander();
@@ -215,7 +886,8 @@ where
         let draw = parse_shared_string_u32(ui_handle.get_draw());
         let cmdr_cmp = if commander && companion { 2 } else { 0 };
 
-        let recommended_lands = land_finder_function(total_cards, ramp, draw, cmdr_cmp);
+        let cards = found_cards(&session.search_results.borrow());
+        let recommended_lands = recommended_lands(total_cards, &cards, ramp, draw, cmdr_cmp);
 
         let answer_str: String = format!("play {} lands", recommended_lands);
         ui_handle.set_answer(answer_str.into());
@@ -226,28 +898,8 @@ where
     Ok(())
 }
 
-fn read_decklist() -> io::Result<Vec<(u32, String)>> {
-    let file = read_to_string(FILE)?;
-    let lines: Vec<String> = file
-        .split(|c| c == '\n' || c == '\r')
-        .filter(|s| s.len() > 0)
-        .map(|s| s.to_string())
-        .collect();
-
-    let mut list: Vec<(u32, String)> = Vec::new();
-
-    for line in lines {
-        let num: u32 = line.split(" ").next().unwrap().parse().unwrap(); //the first symbol of each line is the amount of this card in the list
-        let name_parts: Vec<&str> = line
-            .split(" ")
-            .skip(1) //skip amount of this card
-            .filter(|s| s.len() > 0) //remove empty strs made by double whitespace
-            .take_while(|s| !s.contains("(")) //only read until the set code (AAA)
-            .collect();
-        let card: String = name_parts.join(" ");
-        let regex: String = format!(r"name:/^{}$/", card);
-        list.push((num, regex));
-    }
+fn read_decklist(path: &str) -> Result<Decklist, DecklistError> {
+    let file = read_to_string(path)?;
 
-    Ok(list)
+    decklist::parse(&file)
 }
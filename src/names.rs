@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Lim-Dûl’s Vault"), fold("Lim-Dul's Vault"));
+        assert_eq!(fold("Æther Vial"), fold("aether vial"));
+        assert_eq!(fold("Fire//Ice"), "fire // ice");
+        assert_eq!(fold("Fire / Ice"), "fire // ice");
+        assert_eq!(fold("Who/What/When/Where/Why"), "who/what/when/where/why");
+        assert_eq!(
+            fold("  Tasigur,  the Golden Fang "),
+            "tasigur the golden fang"
+        );
+        assert_ne!(fold("Lim-Dûl's Vault"), fold("Lim-Dûl's Paladin"));
+        assert_eq!(space_faces(" Fire//Ice "), "Fire // Ice");
+    }
+}
+
+/// folds a card name for comparing: lowercase, accents and ligatures spelled out, quotes and other
+/// punctuation dropped, dashes read as spaces and "//" always spaced the same,
+/// so "Lim-Dul's Vault" matches "Lim-Dûl’s Vault" and "Aether" matches "Æther"
+pub fn fold(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => folded.push('a'),
+            'ç' => folded.push('c'),
+            'è' | 'é' | 'ê' | 'ë' | 'ē' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => folded.push('i'),
+            'ñ' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => folded.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'ß' => folded.push_str("ss"),
+            '-' | '‐' | '‑' | '‒' | '–' | '—' | '_' => folded.push(' '),
+            '/' => folded.push('/'),
+            c if c.is_whitespace() => folded.push(' '),
+            c if c.is_alphanumeric() => folded.push(c),
+            // apostrophes, quotes of every style, commas, and the rest
+            _ => {}
+        }
+    }
+
+    folded
+        .replace("//", " // ")
+        .split_whitespace()
+        // "Fire / Ice" is a split card written with one slash
+        .map(|word| if word == "/" { "//" } else { word })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// "Fire//Ice" and "Fire //Ice" the way scryfall spells multi-face names, "Fire // Ice"
+pub fn space_faces(name: &str) -> String {
+    name.split("//")
+        .map(str::trim)
+        .collect::<Vec<&str>>()
+        .join(" // ")
+}
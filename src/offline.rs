@@ -0,0 +1,331 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use uuid::Uuid;
+
+use crate::{
+    bulk_files::{read_cards, BulkError},
+    card::{CardObject, ResponseList, SearchResult},
+    compact::{read_index, write_index, CompactCard},
+    names,
+    query::Query,
+    search::{printing_key, CardQuery, MAX_SUGGESTIONS},
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::TestFolder;
+    use std::fs::read_to_string;
+
+    fn tasigur_index() -> CardIndex {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let mut index = CardIndex::default();
+        response
+            .data
+            .into_iter()
+            .for_each(|card| index.insert((&card).into()));
+        index
+    }
+
+    #[test]
+    fn test_offline_resolve() {
+        let index = tasigur_index();
+        let list = vec![
+            (1, CardQuery::Name("tasigur, the golden fang".to_string())),
+            (
+                2,
+                CardQuery::Printing {
+                    name: "Tasigur, the Golden Fang".to_string(),
+                    set: "uma".to_string(),
+                    collector_number: "117".to_string(),
+                },
+            ),
+            (3, CardQuery::Name("Tasigur".to_string())),
+            (
+                4,
+                CardQuery::OracleId {
+                    name: "Tasigur".to_string(),
+                    oracle_id: index.cards[0].oracle_key(),
+                },
+            ),
+        ];
+
+        let results = index.resolve(list);
+
+        assert!(matches!(&results[0], (1, SearchResult::OneHit(c)) if c.set == "uma"));
+        assert!(matches!(&results[1], (2, SearchResult::OneHit(_))));
+        assert!(
+            matches!(&results[2], (3, SearchResult::NoHits(_, s)) if s == &["Tasigur, the Golden Fang"])
+        );
+        assert!(
+            matches!(&results[3], (4, SearchResult::OneHit(c)) if c.name == "Tasigur, the Golden Fang")
+        );
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let mut index = tasigur_index();
+        let mut german = index.cards[0].clone();
+        german.lang = "de".to_string();
+        german.printed_name = Some("Tasigur, der Goldene Reißzahn".to_string());
+        let english = index.cards[0].clone();
+        index.insert(german.clone());
+        // all_cards has no order between the languages of a printing
+        let mut german_first = CardIndex::default();
+        german_first.insert(german);
+        german_first.insert(english);
+
+        let printing = CardQuery::Printing {
+            name: "Tasigur, the Golden Fang".to_string(),
+            set: "uma".to_string(),
+            collector_number: "117".to_string(),
+        };
+        let results = index.resolve(vec![
+            (
+                1,
+                CardQuery::Name("Tasigur, der goldene Reisszahn".to_string()),
+            ),
+            (1, printing.clone()),
+        ]);
+        let german_first = german_first.resolve(vec![(1, printing)]);
+
+        assert!(matches!(&results[0].1, SearchResult::OneHit(c) if c.lang == "en"));
+        assert!(matches!(&results[1].1, SearchResult::OneHit(c) if c.lang == "en"));
+        assert!(matches!(&german_first[0].1, SearchResult::OneHit(c) if c.lang == "en"));
+    }
+
+    /// offline startup has to stay well under a second for a bulk file the size of oracle_cards
+    #[test]
+    fn test_startup_time() {
+        let tasigur = tasigur_index().cards.remove(0);
+        let cards: Vec<CompactCard> = (0..35_000)
+            .map(|i| CompactCard {
+                id: Uuid::from_u128(i),
+                oracle_id: Some(Uuid::from_u128(i)),
+                name: format!("{} {}", tasigur.name, i),
+                ..tasigur.clone()
+            })
+            .collect();
+        let folder = TestFolder::new("land-calc-test-startup");
+        let path = folder.join("oracle_cards.index");
+        write_index(&path, &cards).unwrap();
+
+        let start = std::time::Instant::now();
+        let mut index = CardIndex::default();
+        read_index(&path)
+            .unwrap()
+            .into_iter()
+            .for_each(|card| index.insert(card));
+        let elapsed = start.elapsed();
+        // a debug build runs several times slower than the release build that is shipped
+        let limit = match cfg!(debug_assertions) {
+            true => std::time::Duration::from_secs(10),
+            false => std::time::Duration::from_secs(1),
+        };
+
+        assert_eq!(index.len(), cards.len());
+        assert!(elapsed < limit, "loading the index took {:?}", elapsed);
+    }
+
+    #[test]
+    fn test_oracle_id() {
+        let index = tasigur_index();
+        let tasigur = &index.cards[0];
+
+        assert_eq!(
+            index.by_oracle_id(tasigur.oracle_id.unwrap()),
+            Some(tasigur)
+        );
+    }
+}
+
+/// every card of a bulk file, findable the ways a decklist names them
+#[derive(Debug, Default)]
+pub struct CardIndex {
+    cards: Vec<CompactCard>,
+    /// every printing of a card, by oracle_id
+    oracle_ids: HashMap<Uuid, Vec<usize>>,
+    /// folded full, face, flavor and printed names, each pointing at the oracle_ids that go by it
+    names: HashMap<String, Vec<Uuid>>,
+    /// "set/collector_number"
+    printings: HashMap<String, usize>,
+    /// cards of the bulk file that couldn't be read, 0 when the index came from the saved one
+    pub skipped: usize,
+}
+
+impl CardIndex {
+    /// reads the compact index saved next to the bulk file, or builds it when the bulk file is newer
+    pub fn load(bulk: &Path) -> Result<Self, BulkError> {
+        let index_path = bulk.with_extension("index");
+        let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if modified(&index_path) >= modified(bulk) {
+            if let Ok(cards) = read_index(&index_path) {
+                let mut index = CardIndex::default();
+                cards.into_iter().for_each(|card| index.insert(card));
+                return Ok(index);
+            }
+        }
+
+        let index = CardIndex::from_bulk(bulk)?;
+        // an index that can't be saved is built again next time
+        let _ = write_index(&index_path, &index.cards);
+        Ok(index)
+    }
+
+    /// reads a bulk file one card at a time into a new index
+    pub fn from_bulk(path: &Path) -> Result<Self, BulkError> {
+        let mut index = CardIndex::default();
+        index.skipped = read_cards(path, |card| index.insert((&card).into()))?.skipped;
+        Ok(index)
+    }
+
+    /// how many printings the index holds
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn insert(&mut self, card: CompactCard) {
+        let i = self.cards.len();
+        let oracle_id = card.oracle_key();
+
+        let face_names = card
+            .faces
+            .iter()
+            .flat_map(|face| [Some(&face.name), face.printed_name.as_ref()])
+            .flatten();
+        for name in [&card.name]
+            .into_iter()
+            .chain(face_names)
+            .chain(&card.flavor_name)
+            .chain(&card.printed_name)
+        {
+            let ids = self.names.entry(names::fold(name)).or_default();
+            if !ids.contains(&oracle_id) {
+                ids.push(oracle_id);
+            }
+        }
+        // every language of a printing has the same set and number, the english one answers for them
+        let printing = self
+            .printings
+            .entry(printing_key(&card.set, &card.collector_number))
+            .or_insert(i);
+        if *printing != i && is_english(&card.lang) && !is_english(&self.cards[*printing].lang) {
+            *printing = i;
+        }
+        self.oracle_ids.entry(oracle_id).or_default().push(i);
+
+        self.cards.push(card);
+    }
+
+    /// the first printing the bulk file had of this card
+    pub fn by_oracle_id(&self, oracle_id: Uuid) -> Option<&CompactCard> {
+        let i = self.oracle_ids.get(&oracle_id)?.first()?;
+        Some(&self.cards[*i])
+    }
+
+    /// every card the query matches, in the order of the bulk file
+    pub fn search(&self, query: &Query) -> Vec<&CompactCard> {
+        self.cards
+            .iter()
+            .filter(|card| query.matches(card))
+            .collect()
+    }
+
+    /// answers like `get_from_scryfall` would, without the network
+    pub fn resolve(&self, list: Vec<(u32, CardQuery)>) -> Vec<(u32, SearchResult)> {
+        list.into_iter()
+            .map(|(amount, query)| (amount, self.find(&query)))
+            .collect()
+    }
+
+    fn find(&self, query: &CardQuery) -> SearchResult {
+        if let CardQuery::Printing {
+            name,
+            set,
+            collector_number,
+        } = query
+        {
+            let printing = self
+                .printings
+                .get(&printing_key(set, collector_number))
+                .map(|i| CardObject::from(&self.cards[*i]))
+                .filter(|card| card.has_name(name));
+
+            if let Some(card) = printing {
+                return SearchResult::OneHit(card);
+            }
+        }
+        if let CardQuery::OracleId { oracle_id, .. } = query {
+            if let Some(card) = self.by_oracle_id(*oracle_id) {
+                return SearchResult::OneHit(self.in_english(card.into()));
+            }
+        }
+
+        let name = query.name();
+        let oracle_ids: &[Uuid] = self
+            .names
+            .get(&names::fold(name))
+            .map_or(&[], Vec::as_slice);
+        let candidates = ResponseList {
+            object: "list".to_string(),
+            total_cards: oracle_ids.len() as u32,
+            has_more: false,
+            data: oracle_ids
+                .iter()
+                .flat_map(|id| &self.oracle_ids[id])
+                .map(|i| (&self.cards[*i]).into())
+                .collect(),
+        };
+
+        match candidates.card_or(name.to_string(), name, query.set()) {
+            SearchResult::OneHit(card) => SearchResult::OneHit(self.in_english(card)),
+            SearchResult::NoHits(search, _) => SearchResult::NoHits(search, self.suggest(name)),
+            result => result,
+        }
+    }
+
+    /// a card found by the name printed in another language, swapped for the english printing if there is one
+    fn in_english(&self, card: CardObject) -> CardObject {
+        if is_english(&card.lang) {
+            return card;
+        }
+
+        let english = self
+            .oracle_ids
+            .get(&card.oracle_key())
+            .and_then(|printings| {
+                printings
+                    .iter()
+                    .map(|i| &self.cards[*i])
+                    .find(|c| is_english(&c.lang))
+            });
+        match english {
+            Some(english) => english.into(),
+            None => card,
+        }
+    }
+
+    /// names that start with what was typed, the offline stand-in for scryfall's autocomplete
+    fn suggest(&self, name: &str) -> Vec<String> {
+        let folded = names::fold(name);
+        let mut suggestions: Vec<String> = self
+            .names
+            .iter()
+            .filter(|(key, _)| !folded.is_empty() && key.starts_with(&folded))
+            .filter_map(|(_, ids)| self.by_oracle_id(*ids.first()?))
+            .map(|card| card.name.clone())
+            .collect();
+
+        suggestions.sort_unstable();
+        suggestions.dedup();
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+}
+
+/// bulk files without languages only have english cards
+fn is_english(lang: &str) -> bool {
+    lang == "en" || lang.is_empty()
+}
@@ -0,0 +1,729 @@
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::{compact::CompactCard, names};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::ResponseList;
+    use std::fs::read_to_string;
+
+    fn tasigur() -> CompactCard {
+        let json_str: String = read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        (&response.data[0]).into()
+    }
+
+    fn is_match(query: &str) -> bool {
+        parse(query).unwrap().matches(&tasigur())
+    }
+
+    #[test]
+    fn test_terms() {
+        assert!(is_match("tasigur"));
+        assert!(is_match(r#"!"Tasigur, the Golden Fang""#));
+        assert!(is_match("name:/^tasigur, the golden fang$/"));
+        assert!(is_match("t:legendary t:shaman o:delve"));
+        assert!(is_match("cmc=6 mv>5 cmc<=6"));
+        assert!(is_match("c:b c=b id:bug id>=ub"));
+        assert!(is_match("is:commander is:permanent f:legacy -is:hybrid"));
+        assert!(!is_match("c:g"));
+        assert!(!is_match("id:b"));
+        assert!(!is_match("produces:c"));
+        assert!(!is_match("is:vanilla"));
+    }
+
+    #[test]
+    fn test_combinators() {
+        assert!(is_match("t:land or t:creature"));
+        assert!(is_match("-t:land (c:r or c:b)"));
+        assert!(!is_match("t:creature -o:delve"));
+        assert!(is_match("t:creature and (cmc<2 or cmc>5)"));
+    }
+
+    #[test]
+    fn test_built_queries() {
+        let search = Query::Term(Term::full_or_face_name("Fire // Ice?")).to_string();
+        assert_eq!(search, r"name:/(^| \/\/ )Fire \/\/ Ice\?($| \/\/ )/");
+
+        let fire_ice = CompactCard {
+            name: "Fire // Ice?".to_string(),
+            ..Default::default()
+        };
+        assert!(parse(&search).unwrap().matches(&fire_ice));
+
+        let face = Query::Term(Term::full_or_face_name("Kellan, Daring Traveler"));
+        let kellan = CompactCard {
+            name: "Kellan, Daring Traveler // Journey On".to_string(),
+            ..Default::default()
+        };
+        assert!(face.matches(&kellan));
+        assert!(!Query::Term(Term::full_or_face_name("Kellan")).matches(&kellan));
+
+        let combined = Query::And(vec![
+            Query::Term(Term::Oracle(r#"say "hi""#.to_string())),
+            Query::Not(Box::new(Query::Or(vec![
+                Query::Term(Term::ManaValue(Compare::Le, 2.0)),
+                Query::Term(Term::Colours(Compare::Ge, ColourValue::Bits(0b00001001))),
+            ]))),
+        ]);
+        let rendered = combined.to_string();
+        assert_eq!(rendered, r#"o:"say \"hi\"" -(mv<=2 or c>=ug)"#);
+        assert_eq!(parse(&rendered).unwrap().to_string(), rendered);
+
+        let not_both = parse("-(t:creature o:delve)").unwrap();
+        let rendered = not_both.to_string();
+        assert_eq!(rendered, r#"-(t:"creature" o:"delve")"#);
+        let bear = CompactCard {
+            type_line: "Creature — Bear".to_string(),
+            ..Default::default()
+        };
+        assert!(not_both.matches(&bear));
+        assert!(parse(&rendered).unwrap().matches(&bear));
+    }
+
+    #[test]
+    fn test_bad_queries() {
+        assert_eq!(parse("").unwrap_err(), QueryError::Empty);
+        assert_eq!(parse("(t:creature").unwrap_err(), QueryError::Unbalanced);
+        assert_eq!(parse("t:creature)").unwrap_err(), QueryError::Unbalanced);
+        assert_eq!(
+            parse("power>3").unwrap_err(),
+            QueryError::UnknownKey("power".to_string())
+        );
+        assert!(matches!(
+            parse("cmc>three"),
+            Err(QueryError::BadValue(_, _))
+        ));
+        assert!(matches!(parse("o:\"deals"), Err(QueryError::Unclosed)));
+    }
+}
+
+/// a parsed search, in the subset of scryfall's syntax that can be answered from the compact index
+#[derive(Debug)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug)]
+pub enum Term {
+    /// a bare word, or `name:`
+    Name(Text),
+    /// `!name`, the whole name or the name of a face
+    ExactName(String),
+    Type(String),
+    Oracle(String),
+    ManaValue(Compare, f64),
+    Colours(Compare, ColourValue),
+    Identity(Compare, ColourValue),
+    /// the mana a card can add, colourless is its own bit
+    Produces(Compare, u8),
+    Is(Is),
+    Legality(Legality, String),
+}
+
+#[derive(Debug)]
+pub enum Text {
+    Contains(String),
+    /// scryfall's regexes ignore case, and so does this one
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColourValue {
+    /// 000WUBRG, like `Colours`
+    Bits(u8),
+    Multicolour,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Is {
+    Permanent,
+    Spell,
+    Historic,
+    Vanilla,
+    Commander,
+    Hybrid,
+    Phyrexian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Legality {
+    /// legal or restricted, like scryfall's `f:`
+    Legal,
+    Banned,
+    Restricted,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryError {
+    /// nothing to search for, or an operator with nothing after it
+    Empty,
+    /// a `(` without its `)`, or the other way around
+    Unbalanced,
+    /// a quote or a regex that never ends
+    Unclosed,
+    UnknownKey(String),
+    /// the key, and the value it can't take
+    BadValue(String, String),
+    BadRegex(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "nothing to search for"),
+            Self::Unbalanced => write!(f, "parentheses don't match up"),
+            Self::Unclosed => write!(f, "a quote or regex is never closed"),
+            Self::UnknownKey(key) => write!(f, "{}: is not something that can be searched", key),
+            Self::BadValue(key, value) => write!(f, "{} can't be searched for {}", key, value),
+            Self::BadRegex(e) => write!(f, "bad regex: {}", e),
+        }
+    }
+}
+
+const PERMANENT_TYPES: [&str; 6] = [
+    "artifact",
+    "creature",
+    "enchantment",
+    "land",
+    "planeswalker",
+    "battle",
+];
+const OPERATORS: [(&str, Option<Compare>); 7] = [
+    (">=", Some(Compare::Ge)),
+    ("<=", Some(Compare::Le)),
+    ("!=", Some(Compare::Ne)),
+    (":", None),
+    ("=", Some(Compare::Eq)),
+    (">", Some(Compare::Gt)),
+    ("<", Some(Compare::Lt)),
+];
+
+pub fn parse(text: &str) -> Result<Query, QueryError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+    };
+
+    let query = parser.or()?;
+    match parser.tokens.next() {
+        None => Ok(query),
+        Some(_) => Err(QueryError::Unbalanced),
+    }
+}
+
+impl Query {
+    pub fn matches(&self, card: &CompactCard) -> bool {
+        match self {
+            Self::And(parts) => parts.iter().all(|q| q.matches(card)),
+            Self::Or(parts) => parts.iter().any(|q| q.matches(card)),
+            Self::Not(query) => !query.matches(card),
+            Self::Term(term) => term.matches(card),
+        }
+    }
+}
+
+impl Term {
+    /// the whole name, or the name of one face of a double faced, split or adventure card,
+    /// whatever regex characters it holds
+    pub fn full_or_face_name(name: &str) -> Self {
+        let pattern = format!("(^| // ){}($| // )", regex::escape(name));
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .expect("an escaped name is a valid regex");
+        Term::Name(Text::Regex(regex))
+    }
+
+    fn matches(&self, card: &CompactCard) -> bool {
+        let mut names = [&card.name]
+            .into_iter()
+            .chain(card.faces.iter().map(|f| &f.name));
+
+        match self {
+            Self::Name(Text::Contains(part)) => names::fold(&card.name).contains(part),
+            Self::Name(Text::Regex(regex)) => names.any(|name| regex.is_match(name)),
+            Self::ExactName(name) => names.any(|n| names::fold(n) == *name),
+            Self::Type(part) => type_lines(card).any(|t| t.to_lowercase().contains(part)),
+            Self::Oracle(part) => {
+                let part = part.replace('~', &card.name.to_lowercase());
+                oracle_texts(card).any(|o| o.to_lowercase().contains(&part))
+            }
+            Self::ManaValue(compare, value) => compare.numbers(card.cmc, *value),
+            Self::Colours(compare, value) => compare.colours(card_colours(card), *value),
+            Self::Identity(compare, value) => compare.colours(card.color_identity, *value),
+            Self::Produces(compare, value) => {
+                compare.sets(colour_bits(card.produced_mana.iter().copied()), *value)
+            }
+            Self::Is(is) => is.matches(card),
+            Self::Legality(legality, format) => {
+                let status = card.legalities.get(format).map(String::as_str);
+                match legality {
+                    Legality::Legal => matches!(status, Some("legal" | "restricted")),
+                    Legality::Banned => status == Some("banned"),
+                    Legality::Restricted => status == Some("restricted"),
+                }
+            }
+        }
+    }
+}
+
+impl Compare {
+    fn numbers(&self, card: f64, value: f64) -> bool {
+        match self {
+            Self::Eq => card == value,
+            Self::Ne => card != value,
+            Self::Lt => card < value,
+            Self::Le => card <= value,
+            Self::Gt => card > value,
+            Self::Ge => card >= value,
+        }
+    }
+
+    /// `Ge` is "has all of these", `Le` is "has nothing but these"
+    fn sets(&self, card: u8, value: u8) -> bool {
+        let superset = card & value == value;
+        let subset = card & !value == 0;
+
+        match self {
+            Self::Eq => card == value,
+            Self::Ne => card != value,
+            Self::Lt => subset && card != value,
+            Self::Le => subset,
+            Self::Gt => superset && card != value,
+            Self::Ge => superset,
+        }
+    }
+
+    fn colours(&self, card: u8, value: ColourValue) -> bool {
+        match value {
+            ColourValue::Bits(bits) => self.sets(card, bits),
+            ColourValue::Multicolour if *self == Self::Ne => card.count_ones() < 2,
+            ColourValue::Multicolour => card.count_ones() >= 2,
+        }
+    }
+}
+
+impl Is {
+    fn matches(&self, card: &CompactCard) -> bool {
+        let type_line = card.type_line.to_lowercase();
+        let mut symbols = card
+            .mana_cost
+            .iter()
+            .chain(card.faces.iter().map(|f| &f.mana_cost))
+            .flat_map(|cost| mana_symbols(cost));
+
+        match self {
+            Self::Permanent => PERMANENT_TYPES.iter().any(|t| type_line.contains(t)),
+            Self::Spell => !type_line.contains("land"),
+            Self::Historic => ["legendary", "artifact", "saga"]
+                .iter()
+                .any(|t| type_line.contains(t)),
+            Self::Vanilla => {
+                type_line.contains("creature") && oracle_texts(card).all(|o| o.is_empty())
+            }
+            Self::Commander => {
+                (type_line.contains("legendary") && type_line.contains("creature"))
+                    || oracle_texts(card).any(|o| o.contains("can be your commander"))
+            }
+            Self::Hybrid => symbols.any(|symbol| symbol.contains('/') && !symbol.ends_with("/P")),
+            Self::Phyrexian => symbols.any(|symbol| symbol.ends_with("/P")),
+        }
+    }
+}
+
+/// "{2}{G/U}" is "2" and "G/U"
+fn mana_symbols(cost: &str) -> impl Iterator<Item = &str> {
+    cost.split('{')
+        .filter_map(|part| part.split_once('}').map(|(symbol, _)| symbol))
+}
+
+fn type_lines(card: &CompactCard) -> impl Iterator<Item = &String> {
+    [&card.type_line]
+        .into_iter()
+        .chain(card.faces.iter().filter_map(|f| f.type_line.as_ref()))
+}
+
+fn oracle_texts(card: &CompactCard) -> impl Iterator<Item = &String> {
+    card.oracle_text
+        .iter()
+        .chain(card.faces.iter().filter_map(|f| f.oracle_text.as_ref()))
+}
+
+/// double faced cards only have colours on their faces
+fn card_colours(card: &CompactCard) -> u8 {
+    card.faces.iter().fold(card.colors, |bits, face| {
+        bits | colour_bits(face.colors.iter().copied())
+    })
+}
+
+/// 000WUBRG, with C as the bit above W
+fn colour_bits(colours: impl Iterator<Item = char>) -> u8 {
+    colours
+        .map(|c| match c.to_ascii_uppercase() {
+            'G' => 0b00000001,
+            'R' => 0b00000010,
+            'B' => 0b00000100,
+            'U' => 0b00001000,
+            'W' => 0b00010000,
+            'C' => 0b00100000,
+            _ => 0,
+        })
+        .fold(0, |bits, bit| bits | bit)
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Word(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut word = String::new();
+                let (mut quoted, mut regex) = (false, false);
+
+                while let Some(&c) = chars.peek() {
+                    if !quoted && !regex && (c.is_whitespace() || c == ')') {
+                        break;
+                    }
+                    chars.next();
+                    word.push(c);
+
+                    match c {
+                        // escapes are kept, the regex or `unquote` deals with them
+                        '\\' if quoted || regex => word.extend(chars.next()),
+                        '"' if !regex => quoted = !quoted,
+                        '/' if regex => regex = false,
+                        '/' if !quoted && (word.ends_with(":/") || word.ends_with("=/")) => {
+                            regex = true
+                        }
+                        _ => {}
+                    }
+                }
+                if quoted || regex {
+                    return Err(QueryError::Unclosed);
+                }
+
+                tokens.push(match word.to_lowercase().as_str() {
+                    "or" => Token::Or,
+                    "and" => Token::And,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut parts = vec![self.and()?];
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            parts.push(self.and()?);
+        }
+
+        Ok(match parts.len() {
+            1 => parts.remove(0),
+            _ => Query::Or(parts),
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut parts = vec![];
+        loop {
+            match self.tokens.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.tokens.next();
+                }
+                Some(_) => parts.push(self.unary()?),
+            }
+        }
+
+        match parts.len() {
+            0 => Err(QueryError::Empty),
+            1 => Ok(parts.remove(0)),
+            _ => Ok(Query::And(parts)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let query = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(QueryError::Unbalanced),
+                }
+            }
+            Some(Token::Word(word)) => term(&word).map(Query::Term),
+            _ => Err(QueryError::Empty),
+        }
+    }
+}
+
+fn term(word: &str) -> Result<Term, QueryError> {
+    if let Some(name) = word.strip_prefix('!') {
+        return Ok(Term::ExactName(names::fold(&unquote(name))));
+    }
+
+    let key_length = word
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(word.len());
+    let (key, rest) = word.split_at(key_length);
+    let operator = OPERATORS.iter().find(|(op, _)| rest.starts_with(op));
+    let Some((op, compare)) = operator.filter(|_| !key.is_empty()) else {
+        return Ok(Term::Name(Text::Contains(names::fold(&unquote(word)))));
+    };
+
+    let key = key.to_lowercase();
+    let value = &rest[op.len()..];
+    let bad_value = || QueryError::BadValue(key.clone(), value.to_string());
+    if value.is_empty() {
+        return Err(bad_value());
+    }
+    let text = || unquote(value).to_lowercase();
+
+    let term = match key.as_str() {
+        "n" | "name" if compare.is_none() => match value.strip_prefix('/') {
+            Some(regex) => {
+                // `\/` only keeps the regex from ending, the pattern itself wants a plain `/`
+                let regex = regex.strip_suffix('/').unwrap_or(regex).replace(r"\/", "/");
+                let regex = RegexBuilder::new(&regex)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| QueryError::BadRegex(e.to_string()))?;
+                Term::Name(Text::Regex(regex))
+            }
+            None => Term::Name(Text::Contains(names::fold(&unquote(value)))),
+        },
+        "t" | "type" if compare.is_none() => Term::Type(text()),
+        "o" | "oracle" if compare.is_none() => Term::Oracle(text()),
+        "cmc" | "mv" | "manavalue" => {
+            let number = value.parse().map_err(|_| bad_value())?;
+            Term::ManaValue(compare.unwrap_or(Compare::Eq), number)
+        }
+        "c" | "color" | "colour" => {
+            let colours = colour_value(&text()).ok_or_else(bad_value)?;
+            // c:c means colourless, not "at least no colours"
+            let default = match colours {
+                ColourValue::Bits(0) => Compare::Eq,
+                _ => Compare::Ge,
+            };
+            Term::Colours(compare.unwrap_or(default), colours)
+        }
+        "id" | "identity" | "ci" => {
+            let colours = colour_value(&text()).ok_or_else(bad_value)?;
+            Term::Identity(compare.unwrap_or(Compare::Le), colours)
+        }
+        "produces" => {
+            let mana = text();
+            if !mana.chars().all(|c| "wubrgc".contains(c)) {
+                return Err(bad_value());
+            }
+            Term::Produces(compare.unwrap_or(Compare::Ge), colour_bits(mana.chars()))
+        }
+        "is" if compare.is_none() => Term::Is(match text().as_str() {
+            "permanent" => Is::Permanent,
+            "spell" => Is::Spell,
+            "historic" => Is::Historic,
+            "vanilla" => Is::Vanilla,
+            "commander" => Is::Commander,
+            "hybrid" => Is::Hybrid,
+            "phyrexian" => Is::Phyrexian,
+            _ => return Err(bad_value()),
+        }),
+        "f" | "format" | "legal" if compare.is_none() => Term::Legality(Legality::Legal, text()),
+        "banned" if compare.is_none() => Term::Legality(Legality::Banned, text()),
+        "restricted" if compare.is_none() => Term::Legality(Legality::Restricted, text()),
+        "n" | "name" | "t" | "type" | "o" | "oracle" | "is" | "f" | "format" | "legal"
+        | "banned" | "restricted" => return Err(bad_value()),
+        _ => return Err(QueryError::UnknownKey(key)),
+    };
+
+    Ok(term)
+}
+
+/// written back out as scryfall syntax, every value quoted or escaped so `parse` reads the same query back
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |parts: &[Query], separator: &str| {
+            parts
+                .iter()
+                .map(Query::to_string)
+                .collect::<Vec<String>>()
+                .join(separator)
+        };
+
+        match self {
+            Self::And(parts) => write!(f, "{}", join(parts, " ")),
+            Self::Or(parts) => write!(f, "({})", join(parts, " or ")),
+            // "-a b" would only negate the first part
+            Self::Not(query) if matches!(**query, Self::And(_)) => write!(f, "-({})", query),
+            Self::Not(query) => write!(f, "-{}", query),
+            Self::Term(term) => write!(f, "{}", term),
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(Text::Contains(part)) => write!(f, "name:{}", quote(part)),
+            Self::Name(Text::Regex(regex)) => {
+                write!(f, "name:/{}/", regex.as_str().replace('/', r"\/"))
+            }
+            Self::ExactName(name) => write!(f, "!{}", quote(name)),
+            Self::Type(part) => write!(f, "t:{}", quote(part)),
+            Self::Oracle(part) => write!(f, "o:{}", quote(part)),
+            Self::ManaValue(compare, value) => write!(f, "mv{}{}", compare, value),
+            Self::Colours(compare, value) => write!(f, "c{}{}", compare, value),
+            Self::Identity(compare, value) => write!(f, "id{}{}", compare, value),
+            Self::Produces(compare, value) => {
+                write!(f, "produces{}{}", compare, colour_letters(*value))
+            }
+            Self::Is(is) => write!(f, "is:{}", is),
+            Self::Legality(Legality::Legal, format) => write!(f, "f:{}", quote(format)),
+            Self::Legality(Legality::Banned, format) => write!(f, "banned:{}", quote(format)),
+            Self::Legality(Legality::Restricted, format) => {
+                write!(f, "restricted:{}", quote(format))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Compare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (op, _) = OPERATORS
+            .iter()
+            .find(|(_, compare)| *compare == Some(*self))
+            .expect("every comparison has an operator");
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for ColourValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bits(0) => write!(f, "c"),
+            Self::Bits(bits) => write!(f, "{}", colour_letters(*bits)),
+            Self::Multicolour => write!(f, "m"),
+        }
+    }
+}
+
+impl fmt::Display for Is {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Permanent => "permanent",
+            Self::Spell => "spell",
+            Self::Historic => "historic",
+            Self::Vanilla => "vanilla",
+            Self::Commander => "commander",
+            Self::Hybrid => "hybrid",
+            Self::Phyrexian => "phyrexian",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// wubrg order, like scryfall writes them, and c for colourless mana
+fn colour_letters(bits: u8) -> String {
+    [
+        ('w', 0b00010000),
+        ('u', 0b00001000),
+        ('b', 0b00000100),
+        ('r', 0b00000010),
+        ('g', 0b00000001),
+        ('c', 0b00100000),
+    ]
+    .iter()
+    .filter(|(_, bit)| bits & bit != 0)
+    .map(|(letter, _)| letter)
+    .collect()
+}
+
+/// the other way round from `unquote`
+fn quote(value: &str) -> String {
+    format!(r#""{}""#, value.replace('\\', r"\\").replace('"', r#"\""#))
+}
+
+fn colour_value(value: &str) -> Option<ColourValue> {
+    let bits = match value {
+        "c" | "colorless" | "colourless" => 0,
+        "m" | "multicolor" | "multicolour" => return Some(ColourValue::Multicolour),
+        "white" => 0b00010000,
+        "blue" => 0b00001000,
+        "black" => 0b00000100,
+        "red" => 0b00000010,
+        "green" => 0b00000001,
+        letters if letters.chars().all(|c| "wubrg".contains(c)) => colour_bits(letters.chars()),
+        _ => return None,
+    };
+    Some(ColourValue::Bits(bits))
+}
+
+/// `"a \"b\""` is `a "b"`, anything not in quotes stays as it is
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => {
+            let mut unquoted = String::new();
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => value.to_string(),
+    }
+}
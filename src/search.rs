@@ -1,62 +1,644 @@
-use crate::card::{ResponseList, SearchResult};
+use crate::{
+    cache::CardCache,
+    card::{
+        CardIdentifier, CardObject, Catalog, CollectionResponse, ResponseList, ScryfallResponse,
+        SearchResult,
+    },
+    client::{RequestError, ScryfallClient},
+    decklist::DeckEntry,
+    names,
+    query::{Query, Term},
+};
 use futures::{stream, StreamExt};
-use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    Client, Response,
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use std::time::Duration;
+use uuid::Uuid;
 
 const CONCURRENT_REQUESTS: usize = 9;
 const TIMEOUT: u64 = 3;
-const SCRYFALL_URL: &str = "https://api.scryfall.com/cards/search";
-pub(crate) const APP_USER_AGENT: &str =
-    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
-const CONNECTION: &str = "keep-alive";
+const SEARCH_PATH: &str = "/cards/search";
+const COLLECTION_PATH: &str = "/cards/collection";
+const NAMED_PATH: &str = "/cards/named";
+const AUTOCOMPLETE_PATH: &str = "/cards/autocomplete";
+/// how many names a "did you mean" offers, online and offline
+pub const MAX_SUGGESTIONS: usize = 3;
+const COLLECTION_SIZE: usize = 75;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::ScryfallError,
+        client::{save_recording, Mode, TestFolder, SCRYFALL_API},
+    };
+    use serde::Serialize;
+    use std::{fs, path::Path};
+
+    fn tasigur() -> CardObject {
+        let json_str = fs::read_to_string("test.json").unwrap();
+        let response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        response.data[0].clone()
+    }
+
+    fn not_found() -> ScryfallError {
+        ScryfallError {
+            object: "error".to_string(),
+            code: "not_found".to_string(),
+            status: 404,
+            details: "No cards found matching the query".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// saves `body` as the answer to `request`
+    fn record(folder: &Path, request: RequestBuilder, body: &impl Serialize) {
+        save_recording(folder, &request, 200, &serde_json::to_string(body).unwrap());
+    }
+
+    /// the `/cards/collection` request `get_from_scryfall` sends first for `list`
+    fn collection_request(client: &ScryfallClient, list: &[(u32, CardQuery)]) -> RequestBuilder {
+        let identifiers: Vec<CardIdentifier> = list.iter().map(|(_, q)| q.into()).collect();
+        client
+            .post(COLLECTION_PATH)
+            .json(&serde_json::json!({ "identifiers": identifiers }))
+    }
+
+    fn search_request(client: &ScryfallClient, name: &str, multilingual: bool) -> RequestBuilder {
+        let search = Query::Term(Term::full_or_face_name(name)).to_string();
+        let request = client.get(SEARCH_PATH).query(&[("q", search.as_str())]);
+        match multilingual {
+            true => request.query(&[("include_multilingual", "true")]),
+            false => request,
+        }
+    }
+
+    #[test]
+    fn test_renamed_keeps_printing() {
+        let printing = CardQuery::Printing {
+            name: "Tasgur".to_string(),
+            set: "frf".to_string(),
+            collector_number: "83".to_string(),
+        };
+
+        assert_eq!(
+            printing.renamed("Tasigur, the Golden Fang"),
+            CardQuery::Printing {
+                name: "Tasigur, the Golden Fang".to_string(),
+                set: "frf".to_string(),
+                collector_number: "83".to_string(),
+            }
+        );
+        assert_eq!(
+            CardQuery::Name("Tasgur".to_string()).renamed("Tasigur, the Golden Fang"),
+            CardQuery::Name("Tasigur, the Golden Fang".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_prefers_set() {
+        let folder = TestFolder::new("land-calc-test-search-set");
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        let json_str = fs::read_to_string("test.json").unwrap();
+        let mut response: ResponseList = serde_json::from_str(&json_str).unwrap();
+        let mut reprint = response.data[0].clone();
+        reprint.set = "frf".to_string();
+        reprint.collector_number = "83".to_string();
+        response.data.insert(0, reprint);
+        response.total_cards = 2;
+
+        let query = CardQuery::Printing {
+            name: "Tasigur, the Golden Fang".to_string(),
+            set: "uma".to_string(),
+            collector_number: "999".to_string(),
+        };
+        let search = Query::Term(Term::full_or_face_name(query.name())).to_string();
+        let request = client
+            .get(SEARCH_PATH)
+            .query(&[("q", search.as_str()), ("unique", "prints")]);
+        save_recording(
+            &folder,
+            &request,
+            200,
+            &serde_json::to_string(&response).unwrap(),
+        );
+
+        let result = search_name(&client, &query, false).await;
+
+        assert!(matches!(result, SearchResult::OneHit(card) if card.set == "uma"));
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_names() {
+        let folder = TestFolder::new("land-calc-test-search-fuzzy");
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        let tasigur = tasigur();
+        let mut vault = tasigur.clone();
+        vault.name = "Lim-Dûl's Vault".to_string();
+        let list = vec![
+            (1, CardQuery::Name("Lim-Dul's Vault".to_string())),
+            (1, CardQuery::Name("Tasgur".to_string())),
+        ];
+        let collected = CollectionResponse {
+            object: "list".to_string(),
+            not_found: list.iter().map(|(_, q)| q.into()).collect(),
+            data: vec![],
+        };
+        let autocomplete = Catalog {
+            object: "catalog".to_string(),
+            total_values: 4,
+            data: vec![
+                "Tasigur, the Golden Fang".to_string(),
+                "Tasigur's Cruelty".to_string(),
+                "Tarmogoyf".to_string(),
+                "Taste of Paradise".to_string(),
+            ],
+        };
+
+        record(&folder, collection_request(&client, &list), &collected);
+        for name in ["Lim-Dul's Vault", "Tasgur"] {
+            for multilingual in [false, true] {
+                record(
+                    &folder,
+                    search_request(&client, name, multilingual),
+                    &not_found(),
+                );
+            }
+        }
+        for (name, card) in [("Lim-Dul's Vault", vault), ("Tasgur", tasigur)] {
+            let fuzzy = client.get(NAMED_PATH).query(&[("fuzzy", name)]);
+            record(&folder, fuzzy, &card);
+        }
+        let suggest = client.get(AUTOCOMPLETE_PATH).query(&[("q", "Tasgur")]);
+        record(&folder, suggest, &autocomplete);
+
+        let results = get_from_scryfall(&client, list).await;
+
+        // only the spelling was off, so scryfall's guess is taken
+        assert!(
+            matches!(&results[0].1, SearchResult::OneHit(card) if card.name == "Lim-Dûl's Vault")
+        );
+        // a guess with another name is only suggested, the fuzzy match first and without repeats
+        assert!(matches!(
+            &results[1].1,
+            SearchResult::NoHits(_, suggestions)
+                if suggestions == &["Tasigur, the Golden Fang", "Tasigur's Cruelty", "Tarmogoyf"]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_results_keep_decklist_order() {
+        let folder = TestFolder::new("land-calc-test-search-order");
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        let tasigur = tasigur();
+        let list = vec![
+            (1, CardQuery::Name(tasigur.name.clone())),
+            (2, CardQuery::Name("Tasgur".to_string())),
+            (3, CardQuery::Name("Opt".to_string())),
+            (4, CardQuery::Name(tasigur.name.clone())),
+        ];
+        let collected = CollectionResponse {
+            object: "list".to_string(),
+            not_found: vec![(&list[1].1).into(), (&list[2].1).into()],
+            data: vec![tasigur.clone(), tasigur],
+        };
 
+        record(&folder, collection_request(&client, &list), &collected);
+        // "Tasgur" is searched for and not found, nothing for "Opt" was recorded so it fails
+        for multilingual in [false, true] {
+            record(
+                &folder,
+                search_request(&client, "Tasgur", multilingual),
+                &not_found(),
+            );
+        }
+
+        let results = get_from_scryfall(&client, list).await;
+
+        assert!(matches!(
+            results.as_slice(),
+            [
+                (1, SearchResult::OneHit(_)),
+                (2, SearchResult::NoHits(_, _)),
+                (3, SearchResult::Failed(CardQuery::Name(opt), _)),
+                (4, SearchResult::OneHit(_)),
+            ] if opt == "Opt"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_second_run_is_cached() {
+        let folder = TestFolder::new("land-calc-test-search-cached");
+        let empty = TestFolder::new("land-calc-test-search-cached-empty");
+        let cache_path = folder.join("cache.json");
+        let tasigur = tasigur();
+        let printing = CardQuery::Printing {
+            name: tasigur.name.clone(),
+            set: tasigur.set.clone(),
+            collector_number: tasigur.collector_number.clone(),
+        };
+        let list = vec![(1, printing.clone())];
+        let collected = CollectionResponse {
+            object: "list".to_string(),
+            not_found: vec![],
+            data: vec![tasigur.clone()],
+        };
+
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        record(&folder, collection_request(&client, &list), &collected);
+        let cache = Mutex::new(CardCache::load(cache_path.clone(), Duration::from_secs(60)));
+        let first = get_cached_or_from_scryfall(&client, &cache, list).await;
+        cache.into_inner().unwrap().save().unwrap();
+
+        // nothing is recorded for the second run, any request it makes fails.
+        // the name is answered by the card the printing cached
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(empty.to_path_buf())).unwrap();
+        let cache = Mutex::new(CardCache::load(cache_path, Duration::from_secs(60)));
+        let list = vec![(1, printing), (2, CardQuery::Name(tasigur.name.clone()))];
+        let second = get_cached_or_from_scryfall(&client, &cache, list).await;
+
+        assert!(matches!(&first[0].1, SearchResult::OneHit(card) if card == &tasigur));
+        assert!(matches!(
+            second.as_slice(),
+            [(1, SearchResult::OneHit(a)), (2, SearchResult::OneHit(b))] if a == &tasigur && b == &tasigur
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_lines_are_retried() {
+        let folder = TestFolder::new("land-calc-test-search-failed");
+        let client = ScryfallClient::new(SCRYFALL_API, Mode::Replay(folder.to_path_buf())).unwrap();
+        let tasigur = tasigur();
+        let mut opt = tasigur.clone();
+        opt.name = "Opt".to_string();
+        let list = vec![
+            (1, CardQuery::Name(tasigur.name.clone())),
+            (4, CardQuery::Name("Opt".to_string())),
+        ];
+        let retried = vec![list[1].clone()];
+        let opt_not_collected = CollectionResponse {
+            object: "list".to_string(),
+            not_found: vec![(&retried[0].1).into()],
+            data: vec![],
+        };
+        let collected = CollectionResponse {
+            data: vec![tasigur],
+            ..opt_not_collected.clone()
+        };
+        record(&folder, collection_request(&client, &list), &collected);
+
+        // the search for opt was never recorded, so it fails like a dropped connection would
+        let results = get_from_scryfall(&client, list).await;
+        let failed: Vec<(u32, CardQuery)> = results
+            .iter()
+            .filter_map(|(amount, result)| match result {
+                SearchResult::Failed(query, _) => Some((*amount, query.clone())),
+                _ => None,
+            })
+            .collect();
+
+        record(
+            &folder,
+            collection_request(&client, &retried),
+            &opt_not_collected,
+        );
+        let opt_found = ResponseList {
+            total_cards: 1,
+            data: vec![opt],
+            ..Default::default()
+        };
+        record(&folder, search_request(&client, "Opt", false), &opt_found);
+        let retry = get_from_scryfall(&client, failed.clone()).await;
+
+        assert!(
+            matches!(&results[0].1, SearchResult::OneHit(card) if card.name.starts_with("Tasigur"))
+        );
+        assert_eq!(failed, retried);
+        assert!(matches!(&retry[0], (4, SearchResult::OneHit(card)) if card.name == "Opt"));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardQuery {
+    /// one exact printing, the name is used if scryfall doesn't know it
+    Printing {
+        name: String,
+        set: String,
+        collector_number: String,
+    },
+    Name(String),
+    /// one card in whatever printing, picked from the candidates of a name that matched several
+    OracleId {
+        name: String,
+        oracle_id: Uuid,
+    },
+}
+
+impl CardQuery {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Printing { name, .. } => name,
+            Self::Name(name) => name,
+            Self::OracleId { name, .. } => name,
+        }
+    }
+
+    pub fn set(&self) -> Option<&str> {
+        match self {
+            Self::Printing { set, .. } => Some(set),
+            Self::Name(_) | Self::OracleId { .. } => None,
+        }
+    }
+
+    /// the same query for another name, a printing keeps its set and collector number
+    pub fn renamed(&self, name: &str) -> CardQuery {
+        match self {
+            Self::Printing {
+                set,
+                collector_number,
+                ..
+            } => Self::Printing {
+                name: name.to_string(),
+                set: set.clone(),
+                collector_number: collector_number.clone(),
+            },
+            Self::Name(_) | Self::OracleId { .. } => Self::Name(name.to_string()),
+        }
+    }
+}
+
+/// how a printing is looked up in the cache and the offline index
+pub fn printing_key(set: &str, collector_number: &str) -> String {
+    format!("{}/{}", set.to_lowercase(), collector_number)
+}
+
+impl From<&DeckEntry> for CardQuery {
+    fn from(entry: &DeckEntry) -> Self {
+        match (&entry.set, &entry.collector_number) {
+            (Some(set), Some(collector_number)) => CardQuery::Printing {
+                name: names::space_faces(&entry.name),
+                set: set.to_lowercase(),
+                collector_number: collector_number.clone(),
+            },
+            _ => CardQuery::Name(names::space_faces(&entry.name)),
+        }
+    }
+}
+
+impl From<&CardQuery> for CardIdentifier {
+    fn from(query: &CardQuery) -> Self {
+        match query {
+            CardQuery::Printing {
+                set,
+                collector_number,
+                ..
+            } => CardIdentifier::Printing {
+                set: set.clone(),
+                collector_number: collector_number.clone(),
+            },
+            CardQuery::Name(name) => CardIdentifier::Name { name: name.clone() },
+            CardQuery::OracleId { oracle_id, .. } => CardIdentifier::OracleId {
+                oracle_id: *oracle_id,
+            },
+        }
+    }
+}
+
+/// resolves the whole list through `/cards/collection`, first by printing and then by name,
+/// what the collection reports as not found is searched for one card at a time.
+/// a request that fails only fails the lines it was asking about.
+/// the results come back in the same order as `list`, however the requests finish
 pub async fn get_from_scryfall(
-    list: Vec<(u32, String)>,
-) -> Result<Vec<(u32, SearchResult)>, reqwest::Error> {
-    let mut headers = HeaderMap::new();
-    headers.insert("User-Agent", HeaderValue::from_static(APP_USER_AGENT));
-    headers.insert("Connection", HeaderValue::from_static(CONNECTION));
-
-    let https_client = Client::builder()
-        .default_headers(headers)
-        .https_only(true)
-        .timeout(Duration::from_secs(TIMEOUT))
-        .build()?;
-
-    let responses = stream::iter(list)
-        .map(|(amount, query)| {
-            let client = &https_client;
-            async move {
-                (
-                    query.to_string(),
-                    scryfall_search(amount, client, &query).await,
-                )
+    client: &ScryfallClient,
+    list: Vec<(u32, CardQuery)>,
+) -> Vec<(u32, SearchResult)> {
+    let identifiers: Vec<CardIdentifier> = list.iter().map(|(_, q)| q.into()).collect();
+    let mut found = scryfall_collection(client, &identifiers).await;
+
+    // a typo in the set or number can point at a different card, so the name has to agree
+    for (card, (_, query)) in found.iter_mut().zip(&list) {
+        if matches!(card, Ok(Some(c)) if !c.has_name(query.name())) {
+            *card = Ok(None);
+        }
+    }
+
+    let unresolved_printings: Vec<usize> = (0..list.len())
+        .filter(|i| {
+            matches!(found[*i], Ok(None)) && matches!(list[*i].1, CardQuery::Printing { .. })
+        })
+        .collect();
+    let by_name: Vec<CardIdentifier> = unresolved_printings
+        .iter()
+        .map(|i| CardIdentifier::Name {
+            name: list[*i].1.name().to_string(),
+        })
+        .collect();
+
+    for (i, card) in unresolved_printings
+        .into_iter()
+        .zip(scryfall_collection(client, &by_name).await)
+    {
+        found[i] = card;
+    }
+
+    let responses = stream::iter(list.into_iter().zip(found))
+        .map(|((amount, query), card)| async move {
+            let name = query.name().to_string();
+            let result = match card {
+                Ok(Some(card)) => SearchResult::OneHit(card),
+                // the collection only knows exact names, a name search is the last chance before NoHits
+                Ok(None) => match search_name(client, &query, false).await {
+                    // maybe a name printed in another language
+                    SearchResult::NoHits(_, _) => search_name(client, &query, true).await,
+                    result => result,
+                },
+                Err(e) => SearchResult::Failed(query, e),
+            };
+
+            match result {
+                SearchResult::NoHits(search, _) => match fuzzy_card(client, &name).await {
+                    // only the spelling was off, like "Lim-Dul's Vault" for "Lim-Dûl's Vault"
+                    Some(card) if card.has_name(&name) => (amount, SearchResult::OneHit(card)),
+                    fuzzy => {
+                        let suggestions = suggest_names(client, &name, fuzzy).await;
+                        (amount, SearchResult::NoHits(search, suggestions))
+                    }
+                },
+                result => (amount, result),
             }
         })
-        .buffer_unordered(CONCURRENT_REQUESTS);
+        .buffered(CONCURRENT_REQUESTS);
+
+    responses.collect::<Vec<(u32, SearchResult)>>().await
+}
+
+/// like `get_from_scryfall`, but whatever `cache` knows isn't asked again,
+/// and every answer scryfall gives is added to it
+pub async fn get_cached_or_from_scryfall(
+    client: &ScryfallClient,
+    cache: &Mutex<CardCache>,
+    list: Vec<(u32, CardQuery)>,
+) -> Vec<(u32, SearchResult)> {
+    let cached: Vec<Option<SearchResult>> = {
+        let cache = cache.lock().expect("cache lock poisoned");
+        list.iter().map(|(_, query)| cache.get(query)).collect()
+    };
 
-    Ok(responses
-        .map(|(q, item)| {
-            let resolved = item.unwrap();
-            (resolved.0, resolved.1.card_or(q))
+    let to_fetch: Vec<(u32, CardQuery)> = list
+        .iter()
+        .zip(&cached)
+        .filter(|(_, result)| result.is_none())
+        .map(|(line, _)| line.clone())
+        .collect();
+    let queries: Vec<CardQuery> = to_fetch.iter().map(|(_, q)| q.clone()).collect();
+    let fetched = if to_fetch.is_empty() {
+        vec![]
+    } else {
+        get_from_scryfall(client, to_fetch).await
+    };
+
+    let mut cache = cache.lock().expect("cache lock poisoned");
+    for (query, (_, result)) in queries.iter().zip(&fetched) {
+        cache.insert(query, result);
+    }
+
+    // the fetched results are in the same order as the lines missing from the cache
+    let mut fetched = fetched.into_iter();
+    list.into_iter()
+        .zip(cached)
+        .map(|((amount, _), result)| match result {
+            Some(result) => (amount, result),
+            None => fetched.next().expect("one result per line looked up"),
         })
-        .collect::<Vec<(u32, SearchResult)>>()
-        .await)
+        .collect()
+}
+
+/// searches for cards with the name, or a face with the name, of `query`
+async fn search_name(
+    client: &ScryfallClient,
+    query: &CardQuery,
+    multilingual: bool,
+) -> SearchResult {
+    let name = query.name();
+    let search = Query::Term(Term::full_or_face_name(name)).to_string();
+
+    let mut options = vec![];
+    if multilingual {
+        options.push(("include_multilingual", "true"));
+    }
+    // scryfall sends one printing of every card by default, which needn't be the one from the listed set
+    if query.set().is_some() {
+        options.push(("unique", "prints"));
+    }
+
+    match scryfall_search(client, &search, &options).await {
+        Ok(ScryfallResponse::Data(response)) => response.card_or(search, name, query.set()),
+        Ok(ScryfallResponse::Error(e)) if e.is_not_found() => SearchResult::NoHits(search, vec![]),
+        Ok(ScryfallResponse::Error(e)) => {
+            SearchResult::Failed(query.clone(), Arc::new(RequestError::Scryfall(Box::new(e))))
+        }
+        Err(e) => SearchResult::Failed(query.clone(), Arc::new(e)),
+    }
+}
+
+/// the card scryfall guesses was meant, if it has a guess
+async fn fuzzy_card(client: &ScryfallClient, name: &str) -> Option<CardObject> {
+    let fuzzy = client
+        .get(NAMED_PATH)
+        .query(&[("fuzzy", name)])
+        .timeout(Duration::from_secs(TIMEOUT));
+
+    match scryfall_json::<CardObject>(client, fuzzy).await {
+        Ok(ScryfallResponse::Data(card)) => Some(card),
+        _ => None,
+    }
+}
+
+/// "did you mean" names for a card scryfall couldn't find, scryfall's fuzzy match first and then
+/// whatever autocomplete makes of it. a failed request just means fewer suggestions
+async fn suggest_names(
+    client: &ScryfallClient,
+    name: &str,
+    fuzzy: Option<CardObject>,
+) -> Vec<String> {
+    let mut names: Vec<String> = fuzzy.into_iter().map(|card| card.name).collect();
+
+    let autocomplete = client
+        .get(AUTOCOMPLETE_PATH)
+        .query(&[("q", name)])
+        .timeout(Duration::from_secs(TIMEOUT));
+    if let Ok(ScryfallResponse::Data(catalog)) =
+        scryfall_json::<Catalog>(client, autocomplete).await
+    {
+        for suggestion in catalog.data {
+            if !names.contains(&suggestion) {
+                names.push(suggestion);
+            }
+        }
+    }
+
+    names.truncate(MAX_SUGGESTIONS);
+    names
 }
 
+/// sends the request and reads the body as either `T` or the error object scryfall sent instead
+async fn scryfall_json<T: DeserializeOwned>(
+    client: &ScryfallClient,
+    request: RequestBuilder,
+) -> Result<ScryfallResponse<T>, RequestError> {
+    let response: Response = client.send(request).await?;
+
+    Ok(response.json::<ScryfallResponse<T>>().await?)
+}
+
+/// `options` are added to the search, like `include_multilingual` or `unique`
 async fn scryfall_search(
-    amount: u32,
-    client: &Client,
+    client: &ScryfallClient,
     query: &str,
-) -> Result<(u32, ResponseList), reqwest::Error> {
-    let response: Response = client
-        .get(SCRYFALL_URL)
+    options: &[(&str, &str)],
+) -> Result<ScryfallResponse<ResponseList>, RequestError> {
+    let request = client
+        .get(SEARCH_PATH)
         .query(&[("q", query)])
-        .send()
-        .await?;
+        .query(options)
+        .timeout(Duration::from_secs(TIMEOUT));
+
+    scryfall_json(client, request).await
+}
+
+/// looks the identifiers up in batches of `COLLECTION_SIZE`, `None` for every card scryfall doesn't know,
+/// and the error for every card in a batch that failed
+async fn scryfall_collection(
+    client: &ScryfallClient,
+    identifiers: &[CardIdentifier],
+) -> Vec<Result<Option<CardObject>, Arc<RequestError>>> {
+    let batches = stream::iter(identifiers.chunks(COLLECTION_SIZE).map(<[_]>::to_vec))
+        .map(|batch| async move {
+            let request = client
+                .post(COLLECTION_PATH)
+                .json(&serde_json::json!({ "identifiers": batch }))
+                .timeout(Duration::from_secs(TIMEOUT));
+            let response = match scryfall_json::<CollectionResponse>(client, request).await {
+                Ok(ScryfallResponse::Data(response)) => Ok(response),
+                Ok(ScryfallResponse::Error(e)) => Err(RequestError::Scryfall(Box::new(e))),
+                Err(e) => Err(e),
+            };
+
+            match response {
+                Ok(response) => response
+                    .match_identifiers(&batch)
+                    .into_iter()
+                    .map(Ok)
+                    .collect(),
+                Err(e) => vec![Err(Arc::new(e)); batch.len()],
+            }
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect::<Vec<Vec<Result<Option<CardObject>, Arc<RequestError>>>>>()
+        .await;
 
-    return Ok((amount, response.json::<ResponseList>().await?));
+    batches.into_iter().flatten().collect()
 }